@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{tokenizer::PlaceholderToken, JsonPathError, JsonPathResult};
+
+/// Values bound to the named placeholders of a jsonpath, e.g. `$id` in `$.users[?(@.id == $id)]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bindings {
+    values: HashMap<String, Value>,
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Bind a value to the placeholder with the given name, without the leading `$`.
+    pub fn bind(mut self, name: &str, value: impl Into<Value>) -> Bindings {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl Into<Value>) -> Option<Value> {
+        self.values.insert(name.to_string(), value.into())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// Get the value bound to the placeholder, and check its type.
+    pub fn resolve(&self, placeholder: &PlaceholderToken) -> JsonPathResult<&Value> {
+        let value = self.get(&placeholder.name).ok_or_else(|| {
+            JsonPathError::EvaluationError(format!(
+                "Missing binding for placeholder ${}",
+                placeholder.name
+            ))
+        })?;
+        if !placeholder.expected.accepts(value) {
+            return Err(JsonPathError::EvaluationError(format!(
+                "Placeholder ${} expects a value of {:?} type, found: {}",
+                placeholder.name, placeholder.expected, value
+            )));
+        }
+        Ok(value)
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, Value)> for Bindings {
+    fn from_iter<T: IntoIterator<Item = (S, Value)>>(iter: T) -> Self {
+        Bindings {
+            values: iter.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::tokenizer::{PlaceholderToken, PlaceholderType};
+
+    use super::Bindings;
+
+    #[test]
+    fn bindings_check_placeholder_types() {
+        let bindings = Bindings::new()
+            .bind("id", 1)
+            .bind("roles", json!(["admin"]));
+        let placeholder = |name: &str, expected| PlaceholderToken {
+            name: name.to_string(),
+            expected,
+        };
+        assert_eq!(
+            Ok(&json!(1)),
+            bindings.resolve(&placeholder("id", PlaceholderType::Any))
+        );
+        assert!(bindings
+            .resolve(&placeholder("roles", PlaceholderType::Array))
+            .is_ok());
+        assert!(bindings
+            .resolve(&placeholder("id", PlaceholderType::Array))
+            .is_err());
+        assert!(bindings
+            .resolve(&placeholder("name", PlaceholderType::Any))
+            .is_err());
+    }
+}
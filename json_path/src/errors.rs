@@ -6,8 +6,11 @@ pub type JsonPathResult<T> = Result<T, JsonPathError>;
 
 #[derive(Debug, PartialEq)]
 pub enum JsonPathError {
+    /// the error message, and the char index within the jsonpath at which parsing stopped
     InvalidJsonPath(String, usize),
     EvaluationError(String),
+    /// a user defined function can not be registered
+    InvalidFunction(String),
 }
 
 impl Error for JsonPathError {}
@@ -21,6 +24,9 @@ impl Display for JsonPathError {
             JsonPathError::EvaluationError(e) => {
                 f.write_fmt(format_args!("JsonPath evaluation error: {}", e))
             }
+            JsonPathError::InvalidFunction(e) => {
+                f.write_fmt(format_args!("Invalid JsonPath function: {}", e))
+            }
         }
     }
 }
@@ -1,448 +1,886 @@
+mod arithmetic;
+mod compare;
+mod functions;
+mod iter;
 mod result_acceptor;
+mod result_sink;
+use arithmetic::*;
+pub use compare::Collation;
+use compare::*;
+use functions::*;
+pub use iter::Iter;
 use result_acceptor::*;
+pub use result_sink::ResultSink;
 
-use std::iter::Peekable;
+use std::{borrow::Cow, cell::RefCell, cmp::Ordering, collections::HashMap, ops::Deref, rc::Rc};
 
 use serde_json::{Map, Value};
 
 use crate::{
     tokenizer::{
-        ArraySlice, Comparator, Expression, PropertyPathToken, RootPathToken, ScanPathToken, Token,
+        result_shape, ArraySlice, Comparator, Expression, FilterMode, PlaceholderToken,
+        ResultShape, Selector, Token,
     },
-    JsonPathError, JsonPathResult,
+    Bindings, JsonPathError, JsonPathResult, NormalizedPath, PathElement,
 };
 
-pub struct Eval {
-    result_acceptor: Box<dyn ResultAcceptor>,
+pub struct Eval<'a, 'b> {
+    /// omit missing properties from merged objects instead of setting them to null
+    omit_missing: bool,
+    bindings: Option<&'b Bindings>,
+    /// location of the node being visited
+    path: NormalizedPath<'a>,
+    /// set when the result sink stops the evaluation
+    stopped: bool,
+    /// the queried document, `$` within filters starts from it
+    root: Option<&'a Value>,
+    /// results of the `$` queries within filters, which are the same for every filtered node
+    root_queries: RefCell<RootQueries<'a>>,
+    collation: Collation,
 }
 
-impl Eval {
+/// results of the `$` queries within filters, by the id assigned to them by the tokenizer
+#[derive(Default)]
+struct RootQueries<'a> {
+    values: HashMap<usize, Rc<Value>>,
+    nodes: HashMap<usize, Rc<Vec<&'a Value>>>,
+}
+
+/// the value of an expression within a filter, nodes of the document and cached results are not
+/// copied
+enum Operand<'a> {
+    /// the absence of a value, e.g. a singular query or `value()` of a query that selects no
+    /// node, which is only equal to itself, see RFC 9535 section 2.3.5.2.2
+    Nothing,
+    Node(&'a Value),
+    Shared(Rc<Value>),
+    Owned(Value),
+}
+
+impl Operand<'_> {
+    fn into_owned(self) -> Value {
+        match self {
+            Operand::Nothing => Value::Null,
+            Operand::Node(value) => value.clone(),
+            Operand::Shared(value) => Rc::try_unwrap(value).unwrap_or_else(|v| (*v).clone()),
+            Operand::Owned(value) => value,
+        }
+    }
+}
+
+impl Deref for Operand<'_> {
+    type Target = Value;
+
+    /// nothing is treated as null by the operators that are not defined by RFC 9535
+    fn deref(&self) -> &Value {
+        match self {
+            Operand::Nothing => &Value::Null,
+            Operand::Node(value) => value,
+            Operand::Shared(value) => value,
+            Operand::Owned(value) => value,
+        }
+    }
+}
+
+/// where the selected nodes go
+enum Output<'a, 's> {
+    /// a scalar or an array result depending on the query, properties selected by the last
+    /// token are merged into one object
+    Result(&'s mut dyn ResultAcceptor<'a>),
+    Sink(&'s mut dyn ResultSink<'a>),
+}
+
+impl Default for Eval<'_, '_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, 'b> Eval<'a, 'b> {
     pub fn new() -> Self {
         Eval {
-            result_acceptor: Box::new(ScalarResultAcceptor::new()),
+            omit_missing: false,
+            bindings: None,
+            path: NormalizedPath::new(),
+            stopped: false,
+            root: None,
+            root_queries: RefCell::default(),
+            collation: Collation::default(),
         }
     }
-    pub fn eval(&mut self, json: &Value, tokens: impl AsRef<Vec<Token>>) -> JsonPathResult<Value> {
-        let mut tokens = tokens.as_ref().iter().peekable();
 
-        match tokens.next() {
-            Some(Token::Root(root)) => self.visit_root(root, json, &mut tokens)?,
-            None => {
-                return Err(JsonPathError::EvaluationError(
-                    "Empty jsonpath provided".to_string(),
-                ))
-            }
-            Some(_) => {
-                return Err(JsonPathError::EvaluationError(
-                    "Invalid start token for the given jsonpath".to_string(),
-                ))
-            }
+    /// An Eval that resolves placeholders with the given bindings.
+    pub fn with_bindings(bindings: &'b Bindings) -> Self {
+        Eval {
+            bindings: Some(bindings),
+            ..Eval::new()
         }
+    }
 
-        self.result_acceptor.result()
+    /// Omit missing properties from the object merged by `['a','b']`, instead of setting them
+    /// to null.
+    pub fn with_omit_missing(mut self, omit_missing: bool) -> Self {
+        self.omit_missing = omit_missing;
+        self
     }
 
-    fn push_result(&mut self, value: Option<Value>) -> JsonPathResult<()> {
-        self.result_acceptor.accept(value)
+    /// Compare strings within filters with the given collation.
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
     }
 
-    fn visit_next_token<'a>(
-        &mut self,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
-    ) -> JsonPathResult<()> {
-        match tokens.next() {
-            Some(Token::Root(_root)) => unimplemented!(),
-            Some(Token::Property(property)) => self.visit_property(property, json, tokens),
-            Some(Token::ArrayIndex { indices }) => self.visit_array_index(indices, json, tokens),
-            Some(Token::ArraySlice(array_slice)) => {
-                self.visit_array_slice(array_slice, json, tokens)
-            }
-            Some(Token::Predicate(expression)) => self.visit_predicate(expression, json, tokens),
-            Some(Token::Function(_)) => todo!(),
-            Some(Token::Scan(scan)) => self.visit_scan(scan, json, tokens),
-            Some(Token::Wildcard) => self.visit_wildchard(json, tokens),
-            None => Ok(()),
+    /// start evaluating a query on the given document
+    fn start(&mut self, root: &'a Value) {
+        self.stopped = false;
+        self.root = Some(root);
+        self.root_queries = RefCell::default();
+    }
+
+    /// evaluate a query within a filter with a new Eval, which shares the root document and the
+    /// results of `$` queries with this one
+    fn sub_eval<T>(
+        &self,
+        f: impl FnOnce(&mut Eval<'a, 'b>) -> JsonPathResult<T>,
+    ) -> JsonPathResult<T> {
+        let mut eval = Eval {
+            bindings: self.bindings,
+            root: self.root,
+            root_queries: RefCell::new(self.root_queries.take()),
+            collation: self.collation,
+            ..Eval::new()
+        };
+        let result = f(&mut eval);
+        self.root_queries.replace(eval.root_queries.into_inner());
+        result
+    }
+
+    /// evaluate a query within a filter to a scalar or an array value, depending on the query,
+    /// a scalar result borrows the selected node
+    fn eval_query(&self, json: &'a Value, tokens: &[Token]) -> JsonPathResult<Operand<'a>> {
+        if result_shape(tokens) == ResultShape::Array {
+            return Ok(Operand::Owned(
+                self.sub_eval(|eval| eval.eval_result(json, tokens))?
+                    .unwrap_or(Value::Null),
+            ));
         }
+        let mut result_acceptor = ScalarResultAcceptor::new();
+        self.sub_eval(|eval| {
+            eval.visit_tokens(json, tokens, &mut Output::Result(&mut result_acceptor))
+        })?;
+        Ok(match result_acceptor.take() {
+            Some(Cow::Borrowed(value)) => Operand::Node(value),
+            Some(Cow::Owned(value)) => Operand::Owned(value),
+            None => Operand::Owned(Value::Null),
+        })
     }
 
-    fn visit_root<'a>(
-        &mut self,
-        _token: &RootPathToken,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
-    ) -> JsonPathResult<()> {
-        match tokens.peek() {
-            None => self.push_result(Some(json.clone())),
-            Some(_) => self.visit_next_token(json, tokens),
+    /// evaluate a query within a filter and return all selected nodes, used for NodesType
+    /// function arguments
+    fn eval_nodes(&self, json: &'a Value, tokens: &[Token]) -> JsonPathResult<Vec<&'a Value>> {
+        self.sub_eval(|eval| {
+            let mut nodes = vec![];
+            eval.visit_tokens(json, tokens, &mut Output::Sink(&mut nodes))?;
+            Ok(nodes)
+        })
+    }
+
+    /// same as [`Eval::eval_query`] for a `$` query, which is only evaluated once per document
+    fn eval_root_query(
+        &self,
+        json: &'a Value,
+        id: usize,
+        tokens: &[Token],
+    ) -> JsonPathResult<Rc<Value>> {
+        if let Some(value) = self.root_queries.borrow().values.get(&id) {
+            return Ok(Rc::clone(value));
+        }
+        let value = Rc::new(
+            self.eval_query(self.root.unwrap_or(json), tokens)?
+                .into_owned(),
+        );
+        self.root_queries
+            .borrow_mut()
+            .values
+            .insert(id, Rc::clone(&value));
+        Ok(value)
+    }
+
+    /// same as [`Eval::eval_nodes`] for a `$` query, which is only evaluated once per document
+    fn eval_root_nodes(
+        &self,
+        json: &'a Value,
+        id: usize,
+        tokens: &[Token],
+    ) -> JsonPathResult<Rc<Vec<&'a Value>>> {
+        if let Some(nodes) = self.root_queries.borrow().nodes.get(&id) {
+            return Ok(Rc::clone(nodes));
         }
+        let nodes = Rc::new(self.eval_nodes(self.root.unwrap_or(json), tokens)?);
+        self.root_queries
+            .borrow_mut()
+            .nodes
+            .insert(id, Rc::clone(&nodes));
+        Ok(nodes)
     }
 
-    fn visit_property<'a>(
+    /// Evaluate the tokens and return a scalar or an array result, depending on the query.
+    pub fn eval(
         &mut self,
-        token: &PropertyPathToken,
-        object: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
-    ) -> JsonPathResult<()> {
-        let object = match object {
-            Value::Object(object) => object,
-            _ => return Ok(()),
+        json: &'a Value,
+        tokens: impl AsRef<Vec<Token>>,
+    ) -> JsonPathResult<Value> {
+        Ok(self.eval_opt(json, tokens)?.unwrap_or(Value::Null))
+    }
+
+    /// Same as [`Eval::eval`], but returns None instead of null when the query should return a
+    /// scalar result and no node is selected.
+    pub fn eval_opt(
+        &mut self,
+        json: &'a Value,
+        tokens: impl AsRef<Vec<Token>>,
+    ) -> JsonPathResult<Option<Value>> {
+        self.start(json);
+        self.eval_result(json, tokens.as_ref())
+    }
+
+    fn eval_result(&mut self, json: &'a Value, tokens: &[Token]) -> JsonPathResult<Option<Value>> {
+        let mut result_acceptor: Box<dyn ResultAcceptor<'a>> = match result_shape(tokens) {
+            ResultShape::Scalar => Box::new(ScalarResultAcceptor::new()),
+            ResultShape::Array => Box::new(ArrayResultRegister::new()),
         };
+        self.visit_tokens(json, tokens, &mut Output::Result(result_acceptor.as_mut()))?;
+        result_acceptor.result()
+    }
 
-        if token.properties.len() > 1 {
-            match tokens.peek() {
-                None => {
-                    // this is a leaf token, will merge properties into one object
-                    let mut result = Map::new();
-                    for prop in token.properties.iter() {
-                        match object.get(prop) {
-                            Some(v) => result.insert(prop.to_string(), v.clone()),
-                            // TODO: differentiate undefined and null with options
-                            None => result.insert(prop.to_string(), Value::Null),
-                        };
-                    }
-                    self.push_result(Some(Value::Object(result)))
-                }
-                Some(_) => {
-                    // this is a multi property iteration
-                    self.use_array_result_register();
+    /// Evaluate the tokens and return all selected nodes in the order they are selected,
+    /// without copying any of them.
+    pub fn eval_ref(
+        &mut self,
+        json: &'a Value,
+        tokens: impl AsRef<Vec<Token>>,
+    ) -> JsonPathResult<Vec<&'a Value>> {
+        let mut nodes = vec![];
+        self.eval_into(json, tokens, &mut nodes)?;
+        Ok(nodes)
+    }
 
-                    for prop in token.properties.iter() {
-                        self.handle_object_property(prop, object, &mut tokens.clone())?;
-                    }
-                    Ok(())
-                }
-            }
-        } else {
-            // single property query
-            let prop = token.properties.first().unwrap();
-            self.handle_object_property(prop, object, tokens)
-        }
+    /// Evaluate the tokens and return all selected nodes along with their locations, in the
+    /// order they are selected.
+    pub fn eval_located(
+        &mut self,
+        json: &'a Value,
+        tokens: impl AsRef<Vec<Token>>,
+    ) -> JsonPathResult<Vec<(NormalizedPath<'a>, &'a Value)>> {
+        let mut located = vec![];
+        self.eval_into(json, tokens, &mut located)?;
+        Ok(located)
     }
 
-    fn handle_object_property<'a>(
+    /// Evaluate the tokens and pass every selected node to the sink as soon as it is selected,
+    /// until the sink stops the evaluation.
+    pub fn eval_into(
         &mut self,
-        prop: &String,
-        object: &Map<String, Value>,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+        json: &'a Value,
+        tokens: impl AsRef<Vec<Token>>,
+        sink: &mut impl ResultSink<'a>,
     ) -> JsonPathResult<()> {
-        match object.get(prop) {
-            Some(v) => match tokens.peek() {
-                None => self.push_result(Some(v.clone())),
-                Some(_) => self.visit_next_token(v, tokens),
-            },
-            None => self.push_result(None),
-        }
+        self.start(json);
+        self.visit_tokens(json, tokens.as_ref(), &mut Output::Sink(sink))
     }
-}
 
-// visit ScanPathToken
-impl Eval {
-    /// upgrade the Eval to return array results
-    fn use_array_result_register(&mut self) {
-        if self.result_acceptor.is_scalar() {
-            self.result_acceptor = Box::new(ArrayResultRegister::new())
-        }
+    /// Evaluate the tokens lazily, the returned iterator selects nodes on demand.
+    pub fn iter<'t>(self, json: &'a Value, tokens: &'t [Token]) -> Iter<'a, 't>
+    where
+        'b: 't,
+    {
+        Iter::new(self, json, tokens)
     }
 
-    fn visit_scan<'a>(
+    fn visit_tokens(
         &mut self,
-        _token: &ScanPathToken,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+        json: &'a Value,
+        tokens: &[Token],
+        out: &mut Output<'a, '_>,
     ) -> JsonPathResult<()> {
-        if !json.is_array() && !json.is_object() {
-            return Err(JsonPathError::EvaluationError(
-                "Properties scan ('..') can only run on array or object values.".to_string(),
-            ));
+        match tokens.split_first() {
+            Some((Token::Root(_), tokens)) => self.visit(json, tokens, out),
+            None => Err(JsonPathError::EvaluationError(
+                "Empty jsonpath provided".to_string(),
+            )),
+            Some(_) => Err(JsonPathError::EvaluationError(
+                "Invalid start token for the given jsonpath".to_string(),
+            )),
         }
-        self.use_array_result_register();
-        self.walk(json, tokens)
     }
 
-    fn walk<'a>(
+    /// apply the first token to the node and the remaining tokens to the nodes it selects, the
+    /// node itself is a result when no token is left
+    fn visit(
         &mut self,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+        json: &'a Value,
+        tokens: &[Token],
+        out: &mut Output<'a, '_>,
     ) -> JsonPathResult<()> {
-        self.visit_next_token(json, &mut tokens.clone())?;
-        match json {
-            Value::Object(object) => {
-                for (_k, v) in object {
-                    self.walk(v, &mut tokens.clone())?;
+        let (token, tokens) = match tokens.split_first() {
+            Some(first) => first,
+            None => return self.push_result(json, out),
+        };
+        if let (
+            Token::Property(property),
+            [],
+            Output::Result(result_acceptor),
+            Value::Object(object),
+        ) = (token, tokens, &mut *out, json)
+        {
+            if property.properties.len() > 1 {
+                // this is a leaf token, will merge properties into one object
+                let mut result = Map::new();
+                for prop in property.properties.iter() {
+                    match object.get(prop) {
+                        Some(v) => {
+                            result.insert(prop.to_string(), v.clone());
+                        }
+                        None if self.omit_missing => {}
+                        None => {
+                            result.insert(prop.to_string(), Value::Null);
+                        }
+                    };
                 }
+                return result_acceptor.accept(Cow::Owned(Value::Object(result)));
             }
-            Value::Array(array) => {
-                for v in array {
-                    self.walk(v, &mut tokens.clone())?;
+        }
+        match self.select(token, json)? {
+            Selection::Nodes(nodes) => {
+                for (element, node) in nodes {
+                    if self.stopped {
+                        break;
+                    }
+                    match element {
+                        Some(element) => {
+                            self.path.push(element);
+                            let result = self.visit(node, tokens, out);
+                            self.path.pop();
+                            result?
+                        }
+                        None => self.visit(node, tokens, out)?,
+                    }
                 }
+                Ok(())
             }
-            _ => {}
+            Selection::Descendants => self.walk(json, tokens, out),
         }
-        Ok(())
     }
-}
 
-// visit array index/slice token
-impl Eval {
-    fn visit_array_index<'a>(
+    /// apply the tokens to the node and all of its descendants
+    fn walk(
         &mut self,
-        indices: &Vec<i32>,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+        json: &'a Value,
+        tokens: &[Token],
+        out: &mut Output<'a, '_>,
     ) -> JsonPathResult<()> {
-        let array = json.as_array().ok_or(JsonPathError::EvaluationError(
-            "Running array index op on non-array object".to_string(),
-        ))?;
-
-        if indices.is_empty() {
-            Err(JsonPathError::EvaluationError(
-                "Invalid array index token, zero index given.".to_string(),
-            ))
-        } else if indices.len() == 1 {
-            let index = *indices.first().unwrap();
-            self.handle_array_index(array, index, tokens)
-        } else {
-            self.use_array_result_register();
-            for index in indices {
-                self.handle_array_index(array, *index, &mut tokens.clone())?;
+        // a trailing scan selects nothing
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        self.visit(json, tokens, out)?;
+        for (element, child) in children(json) {
+            if self.stopped {
+                break;
             }
-            Ok(())
+            self.path.push(element);
+            let result = self.walk(child, tokens, out);
+            self.path.pop();
+            result?
         }
+        Ok(())
     }
 
-    fn handle_array_index<'a>(
-        &mut self,
-        array: &Vec<Value>,
-        mut index: i32,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
-    ) -> JsonPathResult<()> {
-        if index < 0 {
-            // TODO: revisit the cast here
-            index += array.len() as i32;
+    fn push_result(&mut self, value: &'a Value, out: &mut Output<'a, '_>) -> JsonPathResult<()> {
+        if self.stopped {
+            return Ok(());
         }
-        if index >= 0 && index < array.len() as i32 {
-            let value = array.get(index as usize).unwrap();
-            match tokens.peek() {
-                None => self.push_result(Some(value.clone())),
-                Some(_t) => self.visit_next_token(value, tokens),
+        match out {
+            Output::Result(result_acceptor) => result_acceptor.accept(Cow::Borrowed(value)),
+            Output::Sink(sink) => {
+                if sink.accept(&self.path, value).is_break() {
+                    self.stopped = true;
+                }
+                Ok(())
             }
-        } else {
-            Ok(())
         }
     }
 
-    fn visit_array_slice<'a>(
-        &mut self,
-        slice: &ArraySlice,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
-    ) -> JsonPathResult<()> {
-        let array = json.as_array().ok_or(JsonPathError::EvaluationError(
-            "Running array index op on non-array object".to_string(),
-        ))?;
-        self.use_array_result_register();
-        match slice {
-            ArraySlice::From(from) => {
-                let mut start = *from;
-                if start < 0 {
-                    start = (array.len() as i32 + start).max(0);
-                }
-                for index in start..array.len() as i32 {
-                    self.handle_array_index(array, index, &mut tokens.clone())?;
+    fn resolve_placeholder(&self, placeholder: &PlaceholderToken) -> JsonPathResult<&'b Value> {
+        match self.bindings {
+            Some(bindings) => bindings.resolve(placeholder),
+            None => Err(JsonPathError::EvaluationError(format!(
+                "Missing binding for placeholder ${}",
+                placeholder.name
+            ))),
+        }
+    }
+}
+
+/// the nodes selected by a token, along with their location relative to the node it is applied
+/// to, which is None for the node itself
+type Nodes<'a> = Vec<(Option<PathElement<'a>>, &'a Value)>;
+
+/// what a token selects from a node
+enum Selection<'a> {
+    Nodes(Nodes<'a>),
+    /// the node and all of its descendants, for `..`
+    Descendants,
+}
+
+// select the nodes of a single token, shared by the visitor and the lazy iterator
+impl<'a, 'b> Eval<'a, 'b> {
+    fn select(&self, token: &Token, json: &'a Value) -> JsonPathResult<Selection<'a>> {
+        let mut nodes = vec![];
+        match token {
+            Token::Root(_) => {
+                return Err(JsonPathError::EvaluationError(
+                    "Root token is only supported at the start of a jsonpath".to_string(),
+                ))
+            }
+            Token::Property(property) => {
+                for prop in property.properties.iter() {
+                    select_name(prop, json, &mut nodes);
                 }
-                Ok(())
             }
-            ArraySlice::To(to) => {
-                let mut end = *to;
-                if end < 0 {
-                    end += array.len() as i32;
+            Token::ArrayIndex { indices } => {
+                let array = as_array(json)?;
+                if indices.is_empty() {
+                    return Err(JsonPathError::EvaluationError(
+                        "Invalid array index token, zero index given.".to_string(),
+                    ));
                 }
-                for index in 0..end {
-                    self.handle_array_index(array, index, &mut tokens.clone())?;
+                for index in indices {
+                    select_index(*index, array, &mut nodes);
                 }
-                Ok(())
             }
-            ArraySlice::Between(from, to) => {
-                let mut start = *from;
-                let mut end = *to;
-                if end < 0 {
-                    // scenario for eg. [0:-1]
-                    end += array.len() as i32;
-                }
-                if start < 0 {
-                    // scenario for eg. [0:-1]
-                    start = (array.len() as i32 + start).max(0);
+            Token::ArraySlice(slice) => select_slice(slice, as_array(json)?, &mut nodes),
+            Token::Predicate(expression) => self.select_predicate(expression, json, &mut nodes)?,
+            Token::Filter(expression) => self.select_filter(expression, json, &mut nodes)?,
+            Token::Function(function) => {
+                return Err(JsonPathError::EvaluationError(format!(
+                    "Function {}() is only supported within filter expressions",
+                    function.function.name()
+                )))
+            }
+            Token::Scan(_) => {
+                if !json.is_array() && !json.is_object() {
+                    return Err(JsonPathError::EvaluationError(
+                        "Properties scan ('..') can only run on array or object values."
+                            .to_string(),
+                    ));
                 }
-                if start < end && !array.is_empty() {
-                    for index in start..end {
-                        self.handle_array_index(array, index, &mut tokens.clone())?;
+                return Ok(Selection::Descendants);
+            }
+            // a wildcard selects nothing from a primitive value, also within a union
+            Token::Wildcard => select_children(json, &mut nodes),
+            Token::Placeholder(placeholder) => {
+                self.select_placeholder(placeholder, json, &mut nodes)?
+            }
+            // the different kinds of selectors within one pair of square brackets, in order
+            Token::Union(selectors) => {
+                for selector in selectors {
+                    match (selector, json) {
+                        (Selector::Name(name), _) => select_name(name, json, &mut nodes),
+                        (Selector::Index(index), Value::Array(array)) => {
+                            select_index(*index, array, &mut nodes)
+                        }
+                        (Selector::Slice(slice), Value::Array(array)) => {
+                            select_slice(slice, array, &mut nodes)
+                        }
+                        (Selector::Wildcard, _) => select_children(json, &mut nodes),
+                        (Selector::Filter(expression), _) => {
+                            self.select_filter(expression, json, &mut nodes)?
+                        }
+                        (Selector::Predicate(expression), _) => {
+                            self.select_predicate(expression, json, &mut nodes)?
+                        }
+                        (Selector::Placeholder(placeholder), _) => {
+                            self.select_placeholder(placeholder, json, &mut nodes)?
+                        }
+                        // selectors that do not apply to the node select nothing
+                        _ => {}
                     }
                 }
-                Ok(())
             }
         }
+        Ok(Selection::Nodes(nodes))
     }
-}
 
-impl Eval {
-    fn visit_wildchard<'a>(
-        &mut self,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+    fn select_placeholder(
+        &self,
+        placeholder: &PlaceholderToken,
+        json: &'a Value,
+        nodes: &mut Nodes<'a>,
     ) -> JsonPathResult<()> {
-        self.use_array_result_register();
-        match json {
-            Value::Array(array) => {
-                for index in 0..array.len() {
-                    self.handle_array_index(array, index as i32, &mut tokens.clone())?;
-                }
-            }
-            Value::Object(object) => {
-                for prop in object.keys() {
-                    self.handle_object_property(prop, object, &mut tokens.clone())?;
+        match (self.resolve_placeholder(placeholder)?, json) {
+            (Value::String(name), _) => select_name(name, json, nodes),
+            (Value::Number(n), Value::Array(array)) => {
+                if let Some(index) = n.as_i64() {
+                    let index = i32::try_from(index).unwrap_or(i32::MAX);
+                    select_index(index, array, nodes)
                 }
             }
-            _ => {
-                return Err(JsonPathError::EvaluationError(
-                    "Expect array or object for wildcard query.".to_string(),
-                ));
-            }
+            _ => {}
         }
         Ok(())
     }
-}
 
-impl Eval {
-    fn visit_predicate<'a>(
-        &mut self,
+    /// the node itself if the expression is true for it
+    fn select_predicate(
+        &self,
+        expression: &Expression,
+        json: &'a Value,
+        nodes: &mut Nodes<'a>,
+    ) -> JsonPathResult<()> {
+        if Self::get_bool(&*self.eval_expr(expression, json, FilterMode::Compat)?) {
+            nodes.push((None, json));
+        }
+        Ok(())
+    }
+
+    /// the children of the node for which the expression is true, a filter selects nothing from
+    /// a primitive value
+    fn select_filter(
+        &self,
         expression: &Expression,
-        json: &Value,
-        tokens: &mut Peekable<impl Iterator<Item = &'a Token> + Clone>,
+        json: &'a Value,
+        nodes: &mut Nodes<'a>,
     ) -> JsonPathResult<()> {
-        let result = self.eval_expr(expression, json)?;
-        let bool = Self::get_bool(result);
-        match (bool, tokens.peek()) {
-            (true, None) => self.push_result(Some(json.clone())),
-            (true, Some(_)) => self.visit_next_token(json, tokens),
-            _ => Ok(()),
+        for (element, child) in children(json) {
+            if self.eval_test(expression, child)? {
+                nodes.push((Some(element), child));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn as_array(json: &Value) -> JsonPathResult<&Vec<Value>> {
+    json.as_array().ok_or(JsonPathError::EvaluationError(
+        "Running array index op on non-array object".to_string(),
+    ))
+}
+
+fn select_name<'a>(name: &str, json: &'a Value, nodes: &mut Nodes<'a>) {
+    if let Some((k, v)) = json
+        .as_object()
+        .and_then(|object| object.get_key_value(name))
+    {
+        nodes.push((Some(PathElement::Name(Cow::Borrowed(k))), v));
+    }
+}
+
+fn select_index<'a>(index: i32, array: &'a [Value], nodes: &mut Nodes<'a>) {
+    if let Some(index) = normalize_index(index, array.len()) {
+        nodes.push((Some(PathElement::Index(index)), &array[index]));
+    }
+}
+
+fn select_slice<'a>(slice: &ArraySlice, array: &'a [Value], nodes: &mut Nodes<'a>) {
+    nodes.extend(
+        slice
+            .indices(array.len())
+            .map(|index| (Some(PathElement::Index(index)), &array[index])),
+    );
+}
+
+fn select_children<'a>(json: &'a Value, nodes: &mut Nodes<'a>) {
+    nodes.extend(
+        children(json)
+            .into_iter()
+            .map(|(element, child)| (Some(element), child)),
+    );
+}
+
+/// the members of an array or object along with their location, nothing for primitive values
+fn children<'a>(json: &'a Value) -> Vec<(PathElement<'a>, &'a Value)> {
+    match json {
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, v)| (PathElement::Index(index), v))
+            .collect(),
+        Value::Object(object) => object
+            .iter()
+            .map(|(k, v)| (PathElement::Name(Cow::Borrowed(k)), v))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// whether the query selects at most one node and its result is compared as a single node
+fn is_standard_singular(tokens: &[Token], mode: FilterMode) -> bool {
+    mode == FilterMode::Standard && tokens.iter().all(Token::is_singular)
+}
+
+fn first_node<'a>(nodes: &[&'a Value]) -> Operand<'a> {
+    nodes
+        .first()
+        .map_or(Operand::Nothing, |node| Operand::Node(node))
+}
+
+/// the position of an array index, negative indices count from the end of the array
+fn normalize_index(index: i32, len: usize) -> Option<usize> {
+    // TODO: revisit the cast here
+    let index = if index < 0 { index + len as i32 } else { index };
+    if index >= 0 && index < len as i32 {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+impl<'a, 'b> Eval<'a, 'b> {
+    /// evaluate the expression of a filter selector, a query is true when it selects any node
+    fn eval_test(&self, expression: &Expression, json: &'a Value) -> JsonPathResult<bool> {
+        match expression {
+            Expression::JsonQuery(tokens) => Ok(!self.eval_nodes(json, tokens)?.is_empty()),
+            Expression::RootQuery { id, tokens } => {
+                Ok(!self.eval_root_nodes(json, *id, tokens)?.is_empty())
+            }
+            Expression::Not(inner) => Ok(!self.eval_test(inner, json)?),
+            Expression::CompareExpr {
+                op: Comparator::AND,
+                left,
+                right,
+            } => Ok(self.eval_test(left, json)? && self.eval_test(right, json)?),
+            Expression::CompareExpr {
+                op: Comparator::OR,
+                left,
+                right,
+            } => Ok(self.eval_test(left, json)? || self.eval_test(right, json)?),
+            _ => Ok(Self::get_bool(&*self.eval_expr(
+                expression,
+                json,
+                FilterMode::Standard,
+            )?)),
         }
     }
 
-    fn get_bool(value: Value) -> bool {
+    fn get_bool(value: &Value) -> bool {
         match value {
-            Value::Bool(b) => b,
+            Value::Bool(b) => *b,
             Value::Null => false,
             _ => true,
         }
     }
 
-    fn eval_expr(&self, expression: &Expression, json: &Value) -> JsonPathResult<Value> {
+    /// evaluate an expression within a filter, a singular query that selects no node is nothing
+    /// in standard mode, and null in compat mode
+    fn eval_expr(
+        &self,
+        expression: &Expression,
+        json: &'a Value,
+        mode: FilterMode,
+    ) -> JsonPathResult<Operand<'a>> {
         let result = match expression {
-            Expression::JsonQuery(tokens) => {
-                let mut eval = Eval::new();
-                // TODO: support jsonpath query on the root object (using $)
-                eval.eval(json, tokens)?
+            Expression::JsonQuery(tokens) if is_standard_singular(tokens, mode) => {
+                first_node(&self.eval_nodes(json, tokens)?)
+            }
+            Expression::JsonQuery(tokens) => self.eval_query(json, tokens)?,
+            Expression::RootQuery { id, tokens } if is_standard_singular(tokens, mode) => {
+                first_node(&self.eval_root_nodes(json, *id, tokens)?)
+            }
+            Expression::RootQuery { id, tokens } => {
+                Operand::Shared(self.eval_root_query(json, *id, tokens)?)
+            }
+            Expression::Literal(v) => Operand::Owned(v.clone()),
+            Expression::Placeholder(placeholder) => {
+                Operand::Owned(self.resolve_placeholder(placeholder)?.clone())
+            }
+            Expression::Regex(_) => {
+                return Err(JsonPathError::EvaluationError(
+                    "Regular expression can only be used on the right side of =~".to_string(),
+                ))
             }
-            Expression::Literal(v) => v.clone(),
+            Expression::Function(function) => match self.eval_function(function, json, mode)? {
+                FunctionValue::Value(Some(Cow::Borrowed(v))) => Operand::Node(v),
+                FunctionValue::Value(Some(Cow::Owned(v))) => Operand::Owned(v),
+                FunctionValue::Value(None) => Operand::Nothing,
+                FunctionValue::Logical(b) => Operand::Owned(Value::Bool(b)),
+                FunctionValue::Nodes(nodes) => Operand::Owned(Value::Bool(!nodes.is_empty())),
+            },
             Expression::Not(inner) => {
-                let r = self.eval_expr(inner, json)?;
-                match r {
+                let r = self.eval_expr(inner, json, mode)?;
+                Operand::Owned(match *r {
                     Value::Bool(b) => Value::Bool(!b),
                     Value::Null => Value::Bool(true),
                     _ => Value::Bool(false),
-                }
+                })
             }
+            Expression::Negate(inner) => match self.eval_expr(inner, json, mode)? {
+                Operand::Nothing => Operand::Nothing,
+                value => Operand::Owned(negate(&value)?),
+            },
             Expression::Array(v) => {
                 let values = v
                     .iter()
-                    .map(|e| self.eval_expr(e, json))
+                    .map(|e| self.eval_expr(e, json, mode).map(Operand::into_owned))
                     .collect::<JsonPathResult<Vec<Value>>>()?;
-                Value::Array(values)
+                Operand::Owned(Value::Array(values))
+            }
+            Expression::CompareExpr {
+                op: Comparator::RegExpMatch,
+                left,
+                right,
+            } => {
+                let left = self.eval_expr(left, json, mode)?;
+                Operand::Owned(Value::Bool(match (&*left, right.as_ref()) {
+                    (Value::String(s), Expression::Regex(regex)) => regex.is_match(s),
+                    _ => false,
+                }))
+            }
+            Expression::CompareExpr { op, left, right } if op.is_arithmetic() => {
+                match (
+                    self.eval_expr(left, json, mode)?,
+                    self.eval_expr(right, json, mode)?,
+                ) {
+                    (Operand::Nothing, _) | (_, Operand::Nothing) => Operand::Nothing,
+                    (left, right) => Operand::Owned(eval_arithmetic(op, &left, &right)?),
+                }
             }
             Expression::CompareExpr { op, left, right } => {
-                let left = self.eval_expr(left, json)?;
-                let right = self.eval_expr(right, json)?;
-                let result = match op {
-                    Comparator::Eq => left.eq(&right),
-                    Comparator::Neq => !left.eq(&right),
-                    Comparator::Gt => match (left, right) {
-                        (Value::Number(l), Value::Number(r)) => l.as_f64() > r.as_f64(),
-                        _ => false,
-                    },
-                    Comparator::GtEq => match (left, right) {
-                        (Value::Number(l), Value::Number(r)) => l.as_f64() >= r.as_f64(),
-                        _ => false,
-                    },
-                    Comparator::Lt => match (left, right) {
-                        (Value::Number(l), Value::Number(r)) => l.as_f64() < r.as_f64(),
-                        _ => false,
-                    },
-                    Comparator::LtEq => match (left, right) {
-                        (Value::Number(l), Value::Number(r)) => l.as_f64() <= r.as_f64(),
-                        _ => false,
-                    },
-                    Comparator::RegExpMatch => todo!(), // TODO: implement this
-                    Comparator::AND => Self::get_bool(left) && Self::get_bool(right),
-                    Comparator::OR => Self::get_bool(left) || Self::get_bool(right),
-                    Comparator::IN => match right {
-                        Value::Array(values) => values.contains(&left),
-                        _ => false,
-                    },
-                    Comparator::NIN => match right {
-                        Value::Array(values) => !values.contains(&left),
-                        _ => false,
-                    },
-                    Comparator::SubsetOf => match (left, right) {
-                        (Value::Array(l), Value::Array(r)) => l.iter().all(|c| r.contains(c)),
-                        _ => false,
-                    },
-                    Comparator::AnyOf => match (left, right) {
-                        (Value::Array(l), Value::Array(r)) => l.iter().any(|c| r.contains(c)),
-                        _ => false,
-                    },
-                    Comparator::NoneOf => match (left, right) {
-                        (Value::Array(l), Value::Array(r)) => !l.iter().any(|c| r.contains(c)),
-                        _ => false,
-                    },
-                    Comparator::Contains => match (left, right) {
-                        (Value::Array(values), r) => values.contains(&r),
-                        (Value::String(l), Value::String(r)) => l.contains(&r),
-                        _ => false,
-                    },
-                    Comparator::SizeOf => match (left, right) {
-                        (Value::Array(values), Value::Number(n)) => {
-                            values.len() as i64 == n.as_i64().unwrap_or(-1)
-                        }
-                        (Value::String(s), Value::Number(n)) => {
-                            s.len() as i64 == n.as_i64().unwrap_or(-1)
-                        }
-                        _ => false,
-                    },
-                    Comparator::Empty => match (left, right) {
-                        (Value::Array(values), Value::Bool(b)) => values.is_empty() == b,
-                        (Value::String(s), Value::Bool(b)) => s.is_empty() == b,
-                        (Value::Null, Value::Bool(b)) => b,
-                        _ => false,
-                    },
+                let left = self.eval_expr(left, json, mode)?;
+                let right = self.eval_expr(right, json, mode)?;
+                let result = match (op, &left, &right) {
+                    (
+                        Comparator::Eq | Comparator::GtEq | Comparator::LtEq,
+                        Operand::Nothing,
+                        Operand::Nothing,
+                    ) => true,
+                    (Comparator::Neq, Operand::Nothing, Operand::Nothing) => false,
+                    (Comparator::Neq, Operand::Nothing, _)
+                    | (Comparator::Neq, _, Operand::Nothing) => true,
+                    (
+                        Comparator::Eq
+                        | Comparator::Gt
+                        | Comparator::GtEq
+                        | Comparator::Lt
+                        | Comparator::LtEq,
+                        Operand::Nothing,
+                        _,
+                    )
+                    | (
+                        Comparator::Eq
+                        | Comparator::Gt
+                        | Comparator::GtEq
+                        | Comparator::Lt
+                        | Comparator::LtEq,
+                        _,
+                        Operand::Nothing,
+                    ) => false,
+                    _ => self.compare(op, &left, &right),
                 };
-                Value::Bool(result)
+                Operand::Owned(Value::Bool(result))
             }
         };
         Ok(result)
     }
+
+    fn compare(&self, op: &Comparator, left: &Value, right: &Value) -> bool {
+        match op {
+            Comparator::Eq => values_eq_with(left, right, self.collation),
+            Comparator::Neq => !values_eq_with(left, right, self.collation),
+            Comparator::Gt => {
+                compare_values(left, right, self.collation) == Some(Ordering::Greater)
+            }
+            Comparator::GtEq => matches!(
+                compare_values(left, right, self.collation),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Comparator::Lt => compare_values(left, right, self.collation) == Some(Ordering::Less),
+            Comparator::LtEq => matches!(
+                compare_values(left, right, self.collation),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            Comparator::RegExpMatch
+            | Comparator::Add
+            | Comparator::Sub
+            | Comparator::Mul
+            | Comparator::Div
+            | Comparator::Rem => unreachable!(),
+            Comparator::AND => Self::get_bool(left) && Self::get_bool(right),
+            Comparator::OR => Self::get_bool(left) || Self::get_bool(right),
+            Comparator::IN => match right {
+                Value::Array(values) => contains_value(values, left, self.collation),
+                _ => false,
+            },
+            Comparator::NIN => match right {
+                Value::Array(values) => !contains_value(values, left, self.collation),
+                _ => false,
+            },
+            Comparator::SubsetOf => match (left, right) {
+                (Value::Array(l), Value::Array(r)) => {
+                    l.iter().all(|c| contains_value(r, c, self.collation))
+                }
+                _ => false,
+            },
+            Comparator::AnyOf => match (left, right) {
+                (Value::Array(l), Value::Array(r)) => {
+                    l.iter().any(|c| contains_value(r, c, self.collation))
+                }
+                _ => false,
+            },
+            Comparator::NoneOf => match (left, right) {
+                (Value::Array(l), Value::Array(r)) => {
+                    !l.iter().any(|c| contains_value(r, c, self.collation))
+                }
+                _ => false,
+            },
+            Comparator::Contains => match (left, right) {
+                (Value::Array(values), r) => contains_value(values, r, self.collation),
+                (Value::String(l), Value::String(r)) => l.contains(r.as_str()),
+                _ => false,
+            },
+            Comparator::IContains => match (left, right) {
+                (Value::Array(values), r) => contains_value(values, r, Collation::CaseInsensitive),
+                (Value::String(l), Value::String(r)) => {
+                    l.to_lowercase().contains(&r.to_lowercase())
+                }
+                _ => false,
+            },
+            Comparator::StartsWith => matches_strings(left, right, |l, r| l.starts_with(r)),
+            Comparator::EndsWith => matches_strings(left, right, |l, r| l.ends_with(r)),
+            Comparator::IEq => eq_ignore_case(left, right),
+            Comparator::SizeOf => match (left, right) {
+                (Value::Array(values), Value::Number(n)) => {
+                    values.len() as i64 == n.as_i64().unwrap_or(-1)
+                }
+                (Value::String(s), Value::Number(n)) => s.len() as i64 == n.as_i64().unwrap_or(-1),
+                _ => false,
+            },
+            Comparator::Empty => match (left, right) {
+                (Value::Array(values), Value::Bool(b)) => values.is_empty() == *b,
+                (Value::String(s), Value::Bool(b)) => s.is_empty() == *b,
+                (Value::Null, Value::Bool(b)) => *b,
+                _ => false,
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use serde_json::{json, Value};
 
-    use crate::{tokenizer::Tokenizer, JsonPathResult};
+    use crate::{
+        tokenizer::{FilterMode, Tokenizer},
+        JsonPathError, JsonPathResult,
+    };
 
-    use super::Eval;
+    use super::{Collation, Eval, Operand, Token};
 
     pub trait JsonPathQuery {
         fn query(&self, json_path: &str) -> JsonPathResult<Value>;
+
+        /// query with filters parsed in [`FilterMode::Standard`]
+        fn query_standard(&self, json_path: &str) -> JsonPathResult<Value>;
     }
 
     impl JsonPathQuery for Value {
@@ -452,6 +890,13 @@ mod test {
             let mut eval = Eval::new();
             eval.eval(self, tokens)
         }
+
+        fn query_standard(&self, json_path: &str) -> JsonPathResult<Value> {
+            let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+            let tokens = tz.tokenize(json_path)?;
+            let mut eval = Eval::new();
+            eval.eval(self, tokens)
+        }
     }
 
     #[test]
@@ -509,6 +954,20 @@ mod test {
         )
     }
 
+    #[test]
+    fn can_scan_all_descendants() {
+        let json = json!({"a": {"b": [1, {"c": 2}]}, "d": 3});
+        assert_eq!(
+            Ok(json!([{"b": [1, {"c": 2}]}, 3, [1, {"c": 2}], 1, {"c": 2}, 2])),
+            json.query("$..*")
+        );
+        assert_eq!(
+            Ok(json!([[1, {"c": 2}], 1, {"c": 2}, 2])),
+            json.query("$.a..*")
+        );
+        assert_eq!(Ok(json!([2])), json.query("$.a..*.c"));
+    }
+
     #[test]
     fn support_array_index_with_single_index() {
         let json = json!({"data": ["item 0", "item 1", "item 2"]});
@@ -557,6 +1016,366 @@ mod test {
         );
     }
 
+    #[test]
+    fn support_array_slice_with_step() {
+        let json = json!({"data": [0, 1, 2, 3, 4, 5]});
+        assert_eq!(Ok(json!([0, 2, 4])), json.query("$.data[::2]"));
+        assert_eq!(Ok(json!([1, 4])), json.query("$.data[1::3]"));
+        assert_eq!(Ok(json!([5, 4, 3, 2, 1, 0])), json.query("$.data[::-1]"));
+        assert_eq!(Ok(json!([4, 2])), json.query("$.data[-2:0:-2]"));
+        assert_eq!(Ok(json!([0, 1, 2, 3, 4, 5])), json.query("$.data[:]"));
+        assert_eq!(Ok(json!([])), json.query("$.data[::0]"));
+        assert_eq!(Ok(json!([])), json.query("$.data[4:1]"));
+    }
+
+    #[test]
+    fn support_filter_without_parenthesis() {
+        let json = json!({"data": [{"id": 1, "msg": "a"}, {"id": 2}, {"id": 3, "msg": "c"}]});
+        assert_eq!(
+            json.query("$.data[*][?(@.id > 1)].id"),
+            json.query("$.data[*][?@.id > 1].id")
+        );
+        assert_eq!(
+            Ok(json!(["c"])),
+            json.query("$.data[*][?@.id > 1 && @.msg].msg")
+        );
+    }
+
+    #[test]
+    fn support_standard_filters() {
+        let query = |json: &Value, path: &str| json.query_standard(path);
+        let json = json!({"data": [{"id": 1, "msg": "a"}, {"id": 2}, {"id": 3, "msg": "c"}]});
+        assert_eq!(Ok(json!([1, 3])), query(&json, "$.data[?@.msg].id"));
+        assert_eq!(Ok(json!(["c"])), query(&json, "$.data[?(@.id > 1)].msg"));
+        assert_eq!(Ok(json!([])), query(&json, "$.data[0][?@ == 'missing']"));
+        // object members are filtered too, primitive values select nothing
+        assert_eq!(Ok(json!([2])), query(&json, "$.data[1][?@ > 1]"));
+        assert_eq!(Ok(json!([])), query(&json, "$.data[0].id[?@ > 0]"));
+        assert_eq!(
+            Ok(json!([{"id": 1, "msg": "a"}, {"id": 2}])),
+            query(&json, "$.data[0, ?!@.msg]")
+        );
+        // filters are nested by the query within the expression
+        let json = json!({"a": [{"b": [1, 5]}, {"b": [2]}]});
+        assert_eq!(
+            Ok(json!([{"b": [1, 5]}])),
+            query(&json, "$.a[?@.b[?@ > 4]]")
+        );
+        // a query is true when it selects any node, even a null or false one
+        let json = json!([{"a": null}, {"a": false}, {"b": 1}]);
+        assert_eq!(
+            Ok(json!([{"a": null}, {"a": false}])),
+            query(&json, "$[?@.a]")
+        );
+        assert_eq!(Ok(json!([{"b": 1}])), query(&json, "$[?!@.a]"));
+        assert_eq!(
+            Ok(json!([{"a": false}])),
+            query(&json, "$[?@.a && @.a == false]")
+        );
+    }
+
+    #[test]
+    fn standard_filters_do_not_compare_missing_values_with_null() {
+        let json = json!({"data": [{"a": null}, {"b": 2}]});
+        assert_eq!(
+            Ok(json!([])),
+            json.query_standard("$.data[?@.missing == null]")
+        );
+        assert_eq!(
+            Ok(json!([{"a": null}])),
+            json.query_standard("$.data[?@.a == null]")
+        );
+        assert_eq!(
+            Ok(json!([{"b": 2}])),
+            json.query_standard("$.data[?@.a != null]")
+        );
+        assert_eq!(
+            Ok(json!([])),
+            json.query_standard("$.data[?$.missing == null]")
+        );
+        // nothing is only equal to nothing
+        assert_eq!(
+            Ok(json!([{"b": 2}])),
+            json.query_standard("$.data[?@.a == @.missing]")
+        );
+        assert_eq!(Ok(json!([])), json.query_standard("$.data[?@.missing < 1]"));
+        // missing values are null in compat mode
+        assert_eq!(
+            Ok(json!([{"a": null}, {"b": 2}])),
+            json.query("$.data[*][?(@.a == null)]")
+        );
+    }
+
+    #[test]
+    fn support_union_with_filters() {
+        let json = json!({"a": 1, "b": {"x": true}, "c": [0, 1, 2]});
+        assert_eq!(Ok(json!([true, {"x": true}])), json.query("$.b['x', ?@.x]"));
+        assert_eq!(Ok(json!([])), json.query("$.b['y', ?@.y]"));
+        assert_eq!(Ok(json!([2, 0, 0, 1, 2])), json.query("$.c[-1, 0, *]"));
+        // selectors that do not apply to the node select nothing
+        assert_eq!(Ok(json!([0])), json.query("$.c['a', 0]"));
+    }
+
+    #[test]
+    fn support_mixed_union() {
+        let json = json!({"a": [0, 1, 2, 3, 4]});
+        assert_eq!(
+            Ok(json!([0, 2, 3, 0, 1, 2, 3, 4, 4])),
+            json.query("$.a[0, 'name', 2:4, *, -1]")
+        );
+        assert_eq!(Ok(json!([4, 3, 0])), json.query("$.a[-1:-3:-1, 0]"));
+        let json = json!({"a": {"name": "n", "x": {"x": 1}}});
+        assert_eq!(
+            Ok(json!(["n", "n", {"x": 1}])),
+            json.query("$.a[0, 'name', 2:4, *]")
+        );
+    }
+
+    #[test]
+    fn support_root_query_in_filters() {
+        let json = json!({
+            "limits": {"max": 10, "tags": ["a", "b"]},
+            "items": [{"price": 5, "tag": "a"}, {"price": 15, "tag": "b"}, {"price": 8, "tag": "c"}],
+        });
+        assert_eq!(
+            Ok(json!([5, 8])),
+            json.query("$.items[*][?(@.price < $.limits.max)].price")
+        );
+        assert_eq!(
+            Ok(json!(["a", "b"])),
+            json.query("$.items[*][?(@.tag in $.limits.tags)].tag")
+        );
+        assert_eq!(
+            Ok(json!([15])),
+            json.query("$.items[*][?(count($.limits.tags[*]) == 2 && @.price > 10)].price")
+        );
+        // the root document is also used within nested filters
+        assert_eq!(
+            Ok(json!({"price": 5, "tag": "a"})),
+            json.query("$[?(@.items[0][?(@.price < $.limits.max)])].items[0]")
+        );
+    }
+
+    #[test]
+    fn support_json_literals_in_filters() {
+        let json = json!({"data": [
+            {"x": null, "n": 1e6, "point": {"x": 1, "y": 2}, "tags": ["a", "b"]},
+            {"x": 1, "n": 1000, "point": {"x": 2, "y": 1}, "tags": []},
+        ]});
+        assert_eq!(Ok(json!([1e6])), json.query("$.data[*][?(@.x == null)].n"));
+        assert_eq!(Ok(json!([1e6])), json.query("$.data[*][?(@.n >= 1e6)].n"));
+        assert_eq!(Ok(json!([1000])), json.query("$.data[*][?(@.n < 1.5E3)].n"));
+        assert_eq!(
+            Ok(json!([1e6])),
+            json.query(r#"$.data[*][?(@.point == {"x": 1, "y": 2})].n"#)
+        );
+        assert_eq!(
+            Ok(json!([1e6, 1000])),
+            json.query(r#"$.data[*][?(@.tags in [["a","b"], []])].n"#)
+        );
+        assert_eq!(
+            Ok(json!([1e6])),
+            json.query_standard("$.data[?@.x == null].n")
+        );
+        assert_eq!(
+            Ok(json!([1000])),
+            json.query_standard("$.data[?@.n < 1.5E3].n")
+        );
+        assert_eq!(
+            Ok(json!([1e6])),
+            json.query_standard(r#"$.data[?@.point == {"x": 1, "y": 2}].n"#)
+        );
+    }
+
+    #[test]
+    fn support_arithmetic_in_filters() {
+        let json = json!({"data": [
+            {"id": 1, "price": 30, "qty": 4, "start": 0, "end": 7200, "first": "a", "last": "b"},
+            {"id": 2, "price": 2.5, "qty": 10, "start": 100, "end": 200, "first": "c"},
+        ]});
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.data[*][?(@.price * @.qty > 100)].id")
+        );
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.data[*][?(@.price*@.qty > 100 && @.end/@.qty%100 == 0)].id")
+        );
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.data[*][?(@.end - @.start >= 3600)].id")
+        );
+        assert_eq!(
+            Ok(json!([2])),
+            json.query("$.data[*][?(-@.price > -10 && @.qty % 4 == 2)].id")
+        );
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.data[*][?(@.first + ' ' + @.last == 'a b')].id")
+        );
+        // missing values give no result
+        assert_eq!(Ok(json!([])), json.query("$.data[*][?(@.x + 1 > 0)].id"));
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Can not apply * to string and number".to_string()
+            )),
+            json.query("$.data[*][?(@.first * 2 > 0)].id")
+        );
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Division by zero: 7200 / 0".to_string()
+            )),
+            json.query("$.data[*][?(@.end / @.start > 0)].id")
+        );
+        assert_eq!(
+            Ok(json!([1])),
+            json.query_standard("$.data[?@.price * @.qty > 100].id")
+        );
+        assert_eq!(
+            Ok(json!([2])),
+            json.query_standard("$.data[?-@.price > -10 && @.qty % 4 == 2].id")
+        );
+        assert_eq!(
+            Ok(json!([])),
+            json.query_standard("$.data[?@.x + 1 > 0].id")
+        );
+    }
+
+    #[test]
+    fn support_string_ordering() {
+        let json = json!({"events": [
+            {"name": "a", "date": "2023-12-31"},
+            {"name": "b", "date": "2024-01-01"},
+            {"name": "C", "date": "2024-06-15T10:00:00Z"},
+            {"name": "d", "date": 20240101},
+        ]});
+        assert_eq!(
+            Ok(json!(["b", "C"])),
+            json.query("$.events[*][?(@.date >= '2024-01-01')].name")
+        );
+        assert_eq!(
+            Ok(json!(["a"])),
+            json.query("$.events[*][?(@.date < '2024')].name")
+        );
+        assert_eq!(
+            Ok(json!(["a", "b", "d"])),
+            json.query("$.events[*][?(@.name > 'C')].name")
+        );
+
+        let tokens = Tokenizer::new()
+            .with_filter_mode(FilterMode::Standard)
+            .tokenize("$.events[?@.name > 'b'].name")
+            .unwrap();
+        assert_eq!(
+            Ok(json!(["C", "d"])),
+            Eval::new()
+                .with_collation(Collation::CaseInsensitive)
+                .eval(&json, &tokens)
+        );
+
+        let json = json!([
+            {"tags": ["A"], "name": "x"},
+            {"tags": ["b"], "name": "Y"},
+        ]);
+        for (path, expected) in [
+            ("$[*][?(@.tags == ['a'])].name", json!(["x"])),
+            ("$[*][?(@.tags != ['a'])].name", json!(["Y"])),
+            ("$[*][?(@.name in ['X', 'y'])].name", json!(["x", "Y"])),
+            ("$[*][?(@.name nin ['X'])].name", json!(["Y"])),
+            ("$[*][?(@.tags contains 'B')].name", json!(["Y"])),
+            ("$[*][?(@.tags anyof ['a', 'c'])].name", json!(["x"])),
+        ] {
+            let tokens = Tokenizer::new().tokenize(path).unwrap();
+            assert_eq!(
+                Ok(expected),
+                Eval::new()
+                    .with_collation(Collation::CaseInsensitive)
+                    .eval(&json, &tokens),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn filter_queries_borrow_the_selected_node() {
+        let json = json!({"items": [1, 2], "a": {"b": 1}});
+        let tokens = Tokenizer::new().tokenize("$[?(@.items)]").unwrap();
+        let Some(Token::Predicate(expression)) = tokens.last() else {
+            panic!("expected a predicate, got {:?}", tokens);
+        };
+        let eval = Eval::new();
+        match eval.eval_expr(expression, &json, FilterMode::Compat) {
+            Ok(Operand::Node(node)) => assert!(std::ptr::eq(&json["items"], node)),
+            _ => panic!("expected the selected node"),
+        }
+
+        let tokens = Tokenizer::new().tokenize("$[?(@['a', 'b'])]").unwrap();
+        let Some(Token::Predicate(expression)) = tokens.last() else {
+            panic!("expected a predicate, got {:?}", tokens);
+        };
+        match eval.eval_expr(expression, &json, FilterMode::Compat) {
+            Ok(Operand::Owned(value)) => assert_eq!(json!({"a": {"b": 1}, "b": null}), value),
+            _ => panic!("expected the merged properties"),
+        }
+    }
+
+    #[test]
+    fn support_exact_numeric_comparisons() {
+        let json = json!({"data": [
+            {"id": 9_223_372_036_854_775_806_u64, "n": 1, "tags": [1, 2]},
+            {"id": 9_223_372_036_854_775_807_u64, "n": 1.0, "tags": [2.0]},
+            {"id": 18_446_744_073_709_551_615_u64, "n": 1.5},
+        ]});
+        assert_eq!(
+            Ok(json!([9_223_372_036_854_775_807_u64])),
+            json.query("$.data[*][?(@.id == 9223372036854775807)].id")
+        );
+        assert_eq!(
+            Ok(json!([
+                9_223_372_036_854_775_807_u64,
+                18_446_744_073_709_551_615_u64
+            ])),
+            json.query("$.data[*][?(@.id > 9223372036854775806)].id")
+        );
+        assert_eq!(Ok(json!([1, 1.0])), json.query("$.data[*][?(@.n == 1)].n"));
+        assert_eq!(
+            Ok(json!([1, 1.0])),
+            json.query("$.data[*][?(@.n <= 1.0)].n")
+        );
+        assert_eq!(
+            Ok(json!([1.5])),
+            json.query("$.data[*][?(@.n in [1.5, 2])].n")
+        );
+        assert_eq!(
+            Ok(json!([[1, 2], [2.0]])),
+            json.query("$.data[*][?(@.tags contains 2)].tags")
+        );
+        assert_eq!(
+            Ok(json!([18_446_744_073_709_551_615_u64])),
+            json.query("$.data[*][?(@.id - 1 == 18446744073709551614)].id")
+        );
+        assert_eq!(
+            Ok(json!([
+                9_223_372_036_854_775_807_u64,
+                18_446_744_073_709_551_615_u64
+            ])),
+            json.query("$.data[*][?(@.id % 2 == 1)].id")
+        );
+        assert_eq!(
+            Ok(json!([18_446_744_073_709_551_615_u64])),
+            json.query_standard("$.data[?@.id > 9223372036854775807].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 1.0])),
+            json.query_standard("$.data[?@.n <= 1.0].n")
+        );
+        assert_eq!(
+            Ok(json!([9_223_372_036_854_775_806_u64])),
+            json.query_standard("$.data[?@.id != 9223372036854775807 && @.n == 1].id")
+        );
+    }
+
     #[test]
     fn support_wildcard_query_on_objects() {
         let json = json!({"data": {"0": {"msg": "item 0"}, "1": {"msg": "item 1"}}});
@@ -582,6 +1401,11 @@ mod test {
             Ok(json!(["item 0", "item 1"])),
             json.query("$.data[*].msg.msg")
         );
+        // primitive values have no children
+        let json = json!({"a": 1});
+        assert_eq!(Ok(json!([])), json.query("$.a[*]"));
+        assert_eq!(Ok(json!([])), json.query("$.a.*"));
+        assert_eq!(json.query("$.a[*]"), json.query("$.a[*, 0]"));
     }
 
     #[test]
@@ -670,6 +1494,56 @@ mod test {
         );
     }
 
+    #[test]
+    fn support_filters_with_string_matching() {
+        let json = json!({"logs": [
+            {"id": 1, "msg": "ERROR: disk full", "tags": ["Disk", "prod"]},
+            {"id": 2, "msg": "warning: disk almost full", "tags": ["disk"]},
+            {"id": 3, "msg": "error: timeout", "tags": ["net", 1]},
+            {"id": 4, "msg": null},
+        ]});
+        assert_eq!(
+            Ok(json!([3])),
+            json.query("$.logs[*][?(@.msg startswith 'error')].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 2])),
+            json.query("$.logs[*][?(@.msg endsWith 'full')].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 3])),
+            json.query("$.logs[*][?(@.msg icontains 'Error')].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 2])),
+            json.query("$.logs[*][?(@.tags icontains 'DISK')].id")
+        );
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.logs[*][?(@.tags startswith 'pr')].id")
+        );
+        assert_eq!(
+            Ok(json!([2])),
+            json.query("$.logs[*][?(@.msg ieq 'WARNING: Disk Almost Full')].id")
+        );
+        assert_eq!(
+            Ok(json!([3])),
+            json.query("$.logs[*][?(@.tags[1] ieq 1.0)].id")
+        );
+        assert_eq!(
+            Ok(json!([3])),
+            json.query_standard("$.logs[?@.msg startswith 'error'].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 2])),
+            json.query_standard("$.logs[?@.tags icontains 'DISK'].id")
+        );
+        assert_eq!(
+            Ok(json!([1, 2])),
+            json.query_standard("$.logs[?@.msg endswith 'full'].id")
+        );
+    }
+
     #[test]
     fn support_filters_with_sizeof() {
         let json = json!({"data": [{"sizes": ["M", "L"], "id": 10}, {"sizes": ["M",  "XXL"], "id": 11}, {"sizes": ["XXL"], "id": 12}]});
@@ -712,6 +1586,97 @@ mod test {
         assert_eq!(Ok(json!([12])), json.query("$.data[*][?(!@.msg)].id"));
     }
 
+    #[test]
+    fn support_filters_with_regex() {
+        let json = json!({"data": [{"name": "Foo bar", "id": 10}, {"name": "foobar", "id": 11}, {"name": "bar", "id": 12}, {"id": 13}]});
+        assert_eq!(
+            Ok(json!([10, 11])),
+            json.query("$.data[*][?(@.name =~ /^foo.*?/i)].id")
+        );
+        assert_eq!(
+            Ok(json!([11])),
+            json.query("$.data[*][?(@.name =~ /^foo/)].id")
+        );
+        assert_eq!(
+            Ok(json!([12])),
+            json.query("$.data[*][?(!(@.name =~ /foo/i) && @.name)].id")
+        );
+    }
+
+    #[test]
+    fn support_filters_with_length_function() {
+        let json = json!({"data": [{"tags": ["a", "b", "c"], "id": 10}, {"tags": ["a"], "id": 11}, {"tags": "abcd", "id": 12}, {"tags": {"a": 1}, "id": 13}, {"id": 14}]});
+        assert_eq!(
+            Ok(json!([10, 12])),
+            json.query("$.data[*][?(length(@.tags) > 2)].id")
+        );
+        assert_eq!(
+            Ok(json!([11, 13])),
+            json.query("$.data[*][?(length(@.tags) == 1)].id")
+        );
+    }
+
+    #[test]
+    fn support_filters_with_count_function() {
+        let json = json!({"data": [{"tags": ["a", "b", "c"], "id": 10}, {"tags": ["a"], "id": 11}, {"id": 12}]});
+        assert_eq!(
+            Ok(json!([10])),
+            json.query("$.data[*][?(count(@.tags[*]) == 3)].id")
+        );
+        assert_eq!(
+            Ok(json!([12])),
+            json.query("$.data[*][?(count(@['tags', 'id']) == 1)].id")
+        );
+    }
+
+    #[test]
+    fn support_filters_with_match_and_search_functions() {
+        let json = json!({"data": [{"code": "A12"}, {"code": "A12b"}, {"code": "b"}, {"code": 1}]});
+        assert_eq!(
+            Ok(json!(["A12"])),
+            json.query("$.data[*][?(match(@.code, 'A[0-9]+'))].code")
+        );
+        assert_eq!(
+            Ok(json!(["A12", "A12b"])),
+            json.query("$.data[*][?(search(@.code, 'A[0-9]+'))].code")
+        );
+        let json = json!({"data": [{"code": "A12", "pattern": "A.*"}, {"code": "B12", "pattern": "A.*"}, {"code": "C", "pattern": "("}]});
+        assert_eq!(
+            Ok(json!(["A12"])),
+            json.query("$.data[*][?(match(@.code, @.pattern))].code")
+        );
+    }
+
+    #[test]
+    fn support_filters_with_value_function() {
+        let json = json!({"data": [{"items": [{"x": 1}], "id": 10}, {"items": [{"x": 1}, {"x": 2}], "id": 11}, {"items": [], "id": 12}]});
+        assert_eq!(
+            Ok(json!([10])),
+            json.query("$.data[*][?(value(@.items[*].x) == 1)].id")
+        );
+    }
+
+    #[test]
+    fn function_results_without_value_are_not_null() {
+        let json = json!({"data": [{"a": null, "id": 1}, {"b": 2, "id": 2}]});
+        assert_eq!(
+            Ok(json!([1])),
+            json.query("$.data[*][?(value(@.a) == null)].id")
+        );
+        assert_eq!(
+            Ok(json!([2])),
+            json.query("$.data[*][?(value(@.a) != null)].id")
+        );
+        assert_eq!(
+            Ok(json!([2])),
+            json.query("$.data[*][?(value(@.a) == value(@.c))].id")
+        );
+        assert_eq!(
+            Ok(json!([])),
+            json.query("$.data[*][?(length(@.a) + 1 == null)].id")
+        );
+    }
+
     #[test]
     fn support_scan_and_filter() {
         let json = json!([1, 2, 3]);
@@ -0,0 +1,242 @@
+use serde_json::{Number, Value};
+
+use crate::{tokenizer::Comparator, JsonPathError, JsonPathResult};
+
+use super::compare::{exact, Exact};
+
+/// Apply an arithmetic operator, integers stay integers unless the result is a fraction or
+/// overflows. Strings can be concatenated with `+`, a null operand gives a null result.
+pub(super) fn eval_arithmetic(
+    op: &Comparator,
+    left: &Value,
+    right: &Value,
+) -> JsonPathResult<Value> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => eval_numbers(op, l, r),
+        (Value::String(l), Value::String(r)) if *op == Comparator::Add => {
+            Ok(Value::String(format!("{}{}", l, r)))
+        }
+        // a missing value gives no result, so that comparisons with it are false
+        (Value::Null, _) | (_, Value::Null) => Ok(Value::Null),
+        (l, r) => Err(JsonPathError::EvaluationError(format!(
+            "Can not apply {} to {} and {}",
+            symbol(op),
+            type_name(l),
+            type_name(r)
+        ))),
+    }
+}
+
+/// Apply unary minus to a number, a null operand gives a null result.
+pub(super) fn negate(value: &Value) -> JsonPathResult<Value> {
+    match value {
+        Value::Number(n) => match exact(n) {
+            Exact::Int(i) => match int_value(-i) {
+                Some(v) => Ok(v),
+                None => to_value(-(i as f64), || format!("-{}", n)),
+            },
+            Exact::Float(f) => to_value(-f, || format!("-{}", n)),
+        },
+        Value::Null => Ok(Value::Null),
+        v => Err(JsonPathError::EvaluationError(format!(
+            "Can not apply - to {}",
+            type_name(v)
+        ))),
+    }
+}
+
+fn eval_numbers(op: &Comparator, left: &Number, right: &Number) -> JsonPathResult<Value> {
+    // integers within the i64 or u64 range are exact in i128, the result is an integer if it is
+    // within one of these ranges again
+    if let (Exact::Int(l), Exact::Int(r)) = (exact(left), exact(right)) {
+        if r == 0 && matches!(op, Comparator::Div | Comparator::Rem) {
+            return Err(JsonPathError::EvaluationError(format!(
+                "Division by zero: {} {} {}",
+                l,
+                symbol(op),
+                r
+            )));
+        }
+        let result = match op {
+            Comparator::Add => l.checked_add(r),
+            Comparator::Sub => l.checked_sub(r),
+            Comparator::Mul => l.checked_mul(r),
+            Comparator::Div => match l.checked_rem(r) {
+                Some(0) => l.checked_div(r),
+                _ => None,
+            },
+            Comparator::Rem => l.checked_rem(r),
+            _ => unreachable!(),
+        };
+        if let Some(v) = result.and_then(int_value) {
+            return Ok(v);
+        }
+    }
+
+    let (l, r) = (
+        left.as_f64().unwrap_or(f64::NAN),
+        right.as_f64().unwrap_or(f64::NAN),
+    );
+    if r == 0.0 && matches!(op, Comparator::Div | Comparator::Rem) {
+        return Err(JsonPathError::EvaluationError(format!(
+            "Division by zero: {} {} {}",
+            left,
+            symbol(op),
+            right
+        )));
+    }
+    let result = match op {
+        Comparator::Add => l + r,
+        Comparator::Sub => l - r,
+        Comparator::Mul => l * r,
+        Comparator::Div => l / r,
+        Comparator::Rem => l % r,
+        _ => unreachable!(),
+    };
+    to_value(result, || format!("{} {} {}", left, symbol(op), right))
+}
+
+/// an integer as a json number if it is within the i64 or u64 range
+fn int_value(i: i128) -> Option<Value> {
+    match (i64::try_from(i), u64::try_from(i)) {
+        (Ok(i), _) => Some(Value::from(i)),
+        (_, Ok(u)) => Some(Value::from(u)),
+        _ => None,
+    }
+}
+
+fn to_value(f: f64, expr: impl FnOnce() -> String) -> JsonPathResult<Value> {
+    Number::from_f64(f).map(Value::Number).ok_or_else(|| {
+        JsonPathError::EvaluationError(format!("Result of {} is not a finite number", expr()))
+    })
+}
+
+fn symbol(op: &Comparator) -> &'static str {
+    match op {
+        Comparator::Add => "+",
+        Comparator::Sub => "-",
+        Comparator::Mul => "*",
+        Comparator::Div => "/",
+        Comparator::Rem => "%",
+        _ => unreachable!(),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use crate::{tokenizer::Comparator, JsonPathError, JsonPathResult};
+
+    use super::{eval_arithmetic, negate};
+
+    fn eval(op: Comparator, left: Value, right: Value) -> JsonPathResult<Value> {
+        eval_arithmetic(&op, &left, &right)
+    }
+
+    #[test]
+    fn arithmetic_keeps_integer_precision() {
+        let big = 9_007_199_254_740_993_i64; // 2^53 + 1, not representable as f64
+        assert_eq!(
+            Ok(json!(big + 1)),
+            eval(Comparator::Add, json!(big), json!(1))
+        );
+        assert_eq!(Ok(json!(-1)), eval(Comparator::Sub, json!(1), json!(2)));
+        assert_eq!(Ok(json!(6)), eval(Comparator::Mul, json!(2), json!(3)));
+        assert_eq!(Ok(json!(3)), eval(Comparator::Div, json!(6), json!(2)));
+        assert_eq!(Ok(json!(1)), eval(Comparator::Rem, json!(7), json!(3)));
+        assert_eq!(Ok(json!(-big)), negate(&json!(big)));
+    }
+
+    #[test]
+    fn arithmetic_keeps_u64_precision() {
+        assert_eq!(
+            Ok(json!(u64::MAX - 1)),
+            eval(Comparator::Sub, json!(u64::MAX), json!(1))
+        );
+        assert_eq!(
+            Ok(json!(1)),
+            eval(Comparator::Rem, json!(u64::MAX), json!(2))
+        );
+        assert_eq!(
+            Ok(json!(i64::MAX as u64 + 1)),
+            eval(Comparator::Add, json!(i64::MAX), json!(1))
+        );
+        assert_eq!(
+            Ok(json!(i64::MIN)),
+            eval(Comparator::Sub, json!(i64::MAX as u64), json!(u64::MAX))
+        );
+        assert_eq!(
+            Ok(json!(u64::MAX / 5)),
+            eval(Comparator::Div, json!(u64::MAX), json!(5))
+        );
+        assert_eq!(Ok(json!(i64::MAX as u64 + 1)), negate(&json!(i64::MIN)));
+    }
+
+    #[test]
+    fn arithmetic_falls_back_to_floats() {
+        assert_eq!(Ok(json!(3.5)), eval(Comparator::Div, json!(7), json!(2)));
+        assert_eq!(Ok(json!(1.5)), eval(Comparator::Add, json!(1), json!(0.5)));
+        assert_eq!(Ok(json!(1.5)), eval(Comparator::Rem, json!(5.5), json!(2)));
+        assert_eq!(
+            Ok(json!(u64::MAX as f64 * 2.0)),
+            eval(Comparator::Mul, json!(u64::MAX), json!(2))
+        );
+        assert_eq!(
+            Ok(json!(u64::MAX as f64 + 1.0)),
+            eval(Comparator::Add, json!(u64::MAX), json!(1))
+        );
+        assert_eq!(Ok(json!(-(u64::MAX as f64))), negate(&json!(u64::MAX)));
+        assert_eq!(Ok(json!(-0.5)), negate(&json!(0.5)));
+    }
+
+    #[test]
+    fn arithmetic_concatenates_strings() {
+        assert_eq!(
+            Ok(json!("ab")),
+            eval(Comparator::Add, json!("a"), json!("b"))
+        );
+        assert!(eval(Comparator::Mul, json!("a"), json!("b")).is_err());
+    }
+
+    #[test]
+    fn arithmetic_reports_errors() {
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Can not apply + to string and number".to_string()
+            )),
+            eval(Comparator::Add, json!("a"), json!(1))
+        );
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Division by zero: 1 / 0".to_string()
+            )),
+            eval(Comparator::Div, json!(1), json!(0))
+        );
+        assert!(eval(Comparator::Rem, json!(1.5), json!(0.0)).is_err());
+        assert!(eval(Comparator::Mul, json!(1e308), json!(10)).is_err());
+        assert!(eval(Comparator::Sub, json!([1]), json!(1)).is_err());
+        assert!(negate(&json!(true)).is_err());
+        // null operands give null results
+        assert_eq!(
+            Ok(Value::Null),
+            eval(Comparator::Add, Value::Null, json!(1))
+        );
+        assert_eq!(
+            Ok(Value::Null),
+            eval(Comparator::Div, json!(1), Value::Null)
+        );
+        assert_eq!(Ok(Value::Null), negate(&Value::Null));
+    }
+}
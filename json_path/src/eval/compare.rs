@@ -0,0 +1,254 @@
+use std::cmp::Ordering;
+
+use serde_json::{Number, Value};
+
+/// Same as [`values_eq_with`] with the case-insensitive collation.
+pub(super) fn eq_ignore_case(left: &Value, right: &Value) -> bool {
+    values_eq_with(left, right, Collation::CaseInsensitive)
+}
+
+/// Whether the string, or any string within the array, matches the given string.
+pub(super) fn matches_strings(
+    left: &Value,
+    right: &Value,
+    matches: impl Fn(&str, &str) -> bool,
+) -> bool {
+    match (left, right) {
+        (Value::String(l), Value::String(r)) => matches(l, r),
+        (Value::Array(values), Value::String(r)) => values
+            .iter()
+            .any(|v| v.as_str().is_some_and(|l| matches(l, r))),
+        _ => false,
+    }
+}
+
+/// Whether the values contain a value equal to the given one, strings are compared with the
+/// given collation.
+pub(super) fn contains_value(values: &[Value], value: &Value, collation: Collation) -> bool {
+    values.iter().any(|v| values_eq_with(v, value, collation))
+}
+
+/// How strings are compared by `==`, `!=`, `<`, `<=`, `>` and `>=` within filters, as well as
+/// the strings within arrays and objects compared by `==`, `!=`, `in` and `contains`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// compare Unicode scalar values, as defined by RFC 9535
+    #[default]
+    CodePoint,
+    /// compare the lowercase Unicode scalar values, so that `'a' == 'A'`, `'a' <= 'A'` and
+    /// `'A' <= 'a'`
+    CaseInsensitive,
+}
+
+/// Whether the values are equal, numbers are equal if they have the same numeric value, e.g.
+/// `1 == 1.0`, and strings are compared with the given collation, also within arrays and
+/// objects. Object keys are always compared exactly.
+pub(super) fn values_eq_with(left: &Value, right: &Value, collation: Collation) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => compare_numbers(l, r) == Some(Ordering::Equal),
+        (Value::String(l), Value::String(r)) => compare_strings(l, r, collation) == Ordering::Equal,
+        (Value::Array(l), Value::Array(r)) => {
+            l.len() == r.len()
+                && l.iter()
+                    .zip(r)
+                    .all(|(l, r)| values_eq_with(l, r, collation))
+        }
+        (Value::Object(l), Value::Object(r)) => {
+            l.len() == r.len()
+                && l.iter()
+                    .all(|(k, l)| r.get(k).is_some_and(|r| values_eq_with(l, r, collation)))
+        }
+        (l, r) => l == r,
+    }
+}
+
+/// The order of the values, only numbers and strings are ordered.
+pub(super) fn compare_values(
+    left: &Value,
+    right: &Value,
+    collation: Collation,
+) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => compare_numbers(l, r),
+        (Value::String(l), Value::String(r)) => Some(compare_strings(l, r, collation)),
+        _ => None,
+    }
+}
+
+fn compare_strings(left: &str, right: &str, collation: Collation) -> Ordering {
+    match collation {
+        // the byte order of UTF-8 strings is the order of their scalar values
+        Collation::CodePoint => left.cmp(right),
+        Collation::CaseInsensitive => left
+            .chars()
+            .flat_map(char::to_lowercase)
+            .cmp(right.chars().flat_map(char::to_lowercase)),
+    }
+}
+
+/// a number as an exact integer if it is within the i64 or u64 range
+pub(super) enum Exact {
+    Int(i128),
+    Float(f64),
+}
+
+pub(super) fn exact(n: &Number) -> Exact {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) => Exact::Int(i as i128),
+        (_, Some(u)) => Exact::Int(u as i128),
+        // numbers beyond the u64 range, e.g. with serde_json's arbitrary_precision feature, are
+        // compared by their nearest f64 value
+        _ => Exact::Float(n.as_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+/// Compare numbers without losing the precision of 64-bit integers.
+fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    match (exact(left), exact(right)) {
+        (Exact::Int(l), Exact::Int(r)) => Some(l.cmp(&r)),
+        (Exact::Int(l), Exact::Float(r)) => compare_int_float(l, r),
+        (Exact::Float(l), Exact::Int(r)) => compare_int_float(r, l).map(Ordering::reverse),
+        (Exact::Float(l), Exact::Float(r)) => l.partial_cmp(&r),
+    }
+}
+
+fn compare_int_float(i: i128, f: f64) -> Option<Ordering> {
+    // 64-bit integers are within (-2^64, 2^64), larger floats are compared without conversion
+    const LIMIT: f64 = 18_446_744_073_709_551_616.0;
+    if f.is_nan() {
+        None
+    } else if f >= LIMIT {
+        Some(Ordering::Less)
+    } else if f <= -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let trunc = f.trunc();
+        match i.cmp(&(trunc as i128)) {
+            Ordering::Equal => 0.0.partial_cmp(&(f - trunc)),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use serde_json::{json, Value};
+
+    use super::{compare_values, eq_ignore_case, matches_strings, values_eq_with, Collation};
+
+    fn values_eq(left: &Value, right: &Value) -> bool {
+        values_eq_with(left, right, Collation::CodePoint)
+    }
+
+    fn cmp(left: Value, right: Value) -> Option<Ordering> {
+        compare_values(&left, &right, Collation::CodePoint)
+    }
+
+    #[test]
+    fn large_integers_are_compared_exactly() {
+        let max = i64::MAX as u64;
+        assert_eq!(Some(Ordering::Greater), cmp(json!(max), json!(max - 1)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(max), json!(max + 1)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(-1), json!(u64::MAX)));
+        assert_eq!(
+            Some(Ordering::Less),
+            cmp(json!(i64::MIN), json!(i64::MIN + 1))
+        );
+        assert!(!values_eq(&json!(u64::MAX), &json!(u64::MAX - 1)));
+        assert!(values_eq(&json!(u64::MAX), &json!(u64::MAX)));
+    }
+
+    #[test]
+    fn integers_and_floats_are_compared_exactly() {
+        let big = 9_007_199_254_740_993_u64; // 2^53 + 1, the nearest f64 is 2^53
+        assert_eq!(Some(Ordering::Greater), cmp(json!(big), json!(big as f64)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(big as f64), json!(big)));
+        assert_eq!(Some(Ordering::Equal), cmp(json!(1), json!(1.0)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(1), json!(1.5)));
+        assert_eq!(Some(Ordering::Greater), cmp(json!(-1), json!(-1.5)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(0), json!(0.1)));
+        assert_eq!(Some(Ordering::Less), cmp(json!(u64::MAX), json!(1e20)));
+        assert_eq!(Some(Ordering::Greater), cmp(json!(i64::MIN), json!(-1e20)));
+        assert_eq!(
+            Some(Ordering::Less),
+            cmp(json!(u64::MAX), json!(u64::MAX as f64))
+        );
+        assert_eq!(Some(Ordering::Less), cmp(json!(0.1), json!(0.2)));
+    }
+
+    #[test]
+    fn equality_is_numeric() {
+        assert!(values_eq(&json!(1), &json!(1.0)));
+        assert!(values_eq(&json!(-0.0), &json!(0)));
+        assert!(values_eq(&json!([1, {"a": 2.0}]), &json!([1.0, {"a": 2}])));
+        assert!(!values_eq(&json!([1]), &json!([1, 1])));
+        assert!(!values_eq(&json!({"a": 1}), &json!({"b": 1})));
+        assert!(!values_eq(&json!(1), &json!("1")));
+        assert_eq!(None, cmp(json!("a"), json!(1)));
+    }
+
+    #[test]
+    fn strings_are_ordered_by_scalar_values() {
+        assert_eq!(
+            Some(Ordering::Less),
+            cmp(json!("2024-01-01"), json!("2024-01-02"))
+        );
+        assert_eq!(
+            Some(Ordering::Greater),
+            cmp(json!("2024-10-01"), json!("2024-09-30"))
+        );
+        assert_eq!(Some(Ordering::Less), cmp(json!(""), json!("a")));
+        assert_eq!(Some(Ordering::Less), cmp(json!("ab"), json!("abc")));
+        assert_eq!(Some(Ordering::Less), cmp(json!("Z"), json!("a")));
+        // U+FF5E is less than U+1F600, although its UTF-16 encoding is greater
+        assert_eq!(
+            Some(Ordering::Less),
+            cmp(json!("\u{ff5e}"), json!("\u{1f600}"))
+        );
+        assert_eq!(None, cmp(json!("1"), json!(1)));
+        assert_eq!(None, cmp(json!(true), json!(false)));
+        assert_eq!(None, cmp(json!(null), json!(null)));
+    }
+
+    #[test]
+    fn strings_can_be_ordered_case_insensitively() {
+        let cmp =
+            |l: &str, r: &str| compare_values(&json!(l), &json!(r), Collation::CaseInsensitive);
+        assert_eq!(Some(Ordering::Equal), cmp("Abc", "aBC"));
+        assert_eq!(Some(Ordering::Greater), cmp("Z", "a"));
+        assert_eq!(Some(Ordering::Less), cmp("a", "B"));
+        assert_eq!(Some(Ordering::Equal), cmp("ÄÖ", "äö"));
+        assert_eq!(Some(Ordering::Less), cmp("ab", "ABC"));
+        // equality is consistent with the order
+        assert!(values_eq_with(
+            &json!("M"),
+            &json!("m"),
+            Collation::CaseInsensitive
+        ));
+        assert!(!values_eq_with(
+            &json!("M"),
+            &json!("m"),
+            Collation::CodePoint
+        ));
+    }
+
+    #[test]
+    fn strings_can_be_matched() {
+        assert!(eq_ignore_case(&json!("Error"), &json!("ERROR")));
+        assert!(eq_ignore_case(&json!(1), &json!(1.0)));
+        assert!(!eq_ignore_case(&json!("1"), &json!(1)));
+        assert!(eq_ignore_case(
+            &json!(["A", {"b": "C"}]),
+            &json!(["a", {"b": "c"}])
+        ));
+        assert!(!eq_ignore_case(&json!({"B": "c"}), &json!({"b": "c"})));
+        let starts_with = |l: Value, r: Value| matches_strings(&l, &r, |l, r| l.starts_with(r));
+        assert!(starts_with(json!("error: disk full"), json!("error")));
+        assert!(starts_with(json!([1, "warn", "error: x"]), json!("error")));
+        assert!(!starts_with(json!(["warn"]), json!("error")));
+        assert!(!starts_with(json!("1"), json!(1)));
+        assert!(!starts_with(json!(null), json!("")));
+    }
+}
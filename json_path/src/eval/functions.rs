@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::{
+    tokenizer::{Expression, FilterMode, Function, FunctionPathToken, FunctionType, RegexLiteral},
+    JsonPathResult,
+};
+
+use super::{Eval, Operand};
+
+/// Values of function arguments and results, see RFC 9535 section 2.4.1.
+/// Nodes are borrowed from the json document whenever possible.
+pub enum FunctionValue<'a> {
+    /// a json value, None for nothing
+    Value(Option<Cow<'a, Value>>),
+    Logical(bool),
+    Nodes(Vec<Cow<'a, Value>>),
+}
+
+impl<'a, 'b> Eval<'a, 'b> {
+    pub(super) fn eval_function(
+        &self,
+        function: &FunctionPathToken,
+        json: &'a Value,
+        mode: FilterMode,
+    ) -> JsonPathResult<FunctionValue<'a>> {
+        let result = match &function.function {
+            Function::Length => {
+                let length = match self
+                    .eval_value_arg(&function.args[0], json, mode)?
+                    .as_deref()
+                {
+                    Some(Value::String(s)) => Some(s.chars().count()),
+                    Some(Value::Array(values)) => Some(values.len()),
+                    Some(Value::Object(object)) => Some(object.len()),
+                    _ => None,
+                };
+                FunctionValue::Value(length.map(|l| Cow::Owned(l.into())))
+            }
+            Function::Count => {
+                let nodes = self.eval_nodes_arg(&function.args[0], json, mode)?;
+                FunctionValue::Value(Some(Cow::Owned(nodes.len().into())))
+            }
+            Function::Value => {
+                let mut nodes = self.eval_nodes_arg(&function.args[0], json, mode)?;
+                match nodes.len() {
+                    1 => FunctionValue::Value(nodes.pop()),
+                    _ => FunctionValue::Value(None),
+                }
+            }
+            Function::Custom(custom) => {
+                let args = custom
+                    .parameters()
+                    .iter()
+                    .zip(function.args.iter())
+                    .map(|(parameter, arg)| match parameter {
+                        FunctionType::ValueType => Ok(self
+                            .eval_value_arg(arg, json, mode)?
+                            .map(Cow::into_owned)
+                            .unwrap_or(Value::Null)),
+                        // a query argument is true when it selects any node
+                        FunctionType::LogicalType => Ok(Value::Bool(self.eval_test(arg, json)?)),
+                        FunctionType::NodesType => {
+                            let nodes = self.eval_nodes_arg(arg, json, mode)?;
+                            Ok(Value::Array(
+                                nodes.into_iter().map(Cow::into_owned).collect(),
+                            ))
+                        }
+                    })
+                    .collect::<JsonPathResult<Vec<Value>>>()?;
+                match (custom.result_type(), custom.call(&args)) {
+                    (FunctionType::ValueType, v) => FunctionValue::Value(Some(Cow::Owned(v))),
+                    (FunctionType::LogicalType, v) => FunctionValue::Logical(Eval::get_bool(&v)),
+                    (FunctionType::NodesType, Value::Array(nodes)) => {
+                        FunctionValue::Nodes(nodes.into_iter().map(Cow::Owned).collect())
+                    }
+                    (FunctionType::NodesType, v) => FunctionValue::Nodes(vec![Cow::Owned(v)]),
+                }
+            }
+            f @ (Function::Match | Function::Search) => {
+                let s = match self.eval_value_arg(&function.args[0], json, mode)? {
+                    Some(s) if s.is_string() => s,
+                    _ => return Ok(FunctionValue::Logical(false)),
+                };
+                let s = s.as_str().unwrap_or_default();
+                let matched = match &function.args[1] {
+                    // compiled by the tokenizer
+                    Expression::Regex(regex) => regex.is_match(s),
+                    arg => {
+                        let regex = match (self.eval_value_arg(arg, json, mode)?.as_deref(), f) {
+                            (Some(Value::String(p)), Function::Match) => {
+                                RegexLiteral::full_match(p.clone())
+                            }
+                            (Some(Value::String(p)), _) => {
+                                RegexLiteral::new(p.clone(), String::new())
+                            }
+                            _ => return Ok(FunctionValue::Logical(false)),
+                        };
+                        // an invalid regular expression does not match anything
+                        regex.map(|r| r.is_match(s)).unwrap_or(false)
+                    }
+                };
+                FunctionValue::Logical(matched)
+            }
+        };
+        Ok(result)
+    }
+
+    fn eval_value_arg(
+        &self,
+        arg: &Expression,
+        json: &'a Value,
+        mode: FilterMode,
+    ) -> JsonPathResult<Option<Cow<'a, Value>>> {
+        match arg {
+            Expression::JsonQuery(tokens) => Ok(self
+                .eval_nodes(json, tokens)?
+                .first()
+                .map(|v| Cow::Borrowed(*v))),
+            Expression::RootQuery { id, tokens } => Ok(self
+                .eval_root_nodes(json, *id, tokens)?
+                .first()
+                .map(|v| Cow::Borrowed(*v))),
+            Expression::Function(f) if f.function.result_type() == FunctionType::ValueType => {
+                match self.eval_function(f, json, mode)? {
+                    FunctionValue::Value(v) => Ok(v),
+                    _ => Ok(None),
+                }
+            }
+            arg => Ok(match self.eval_expr(arg, json, mode)? {
+                Operand::Nothing => None,
+                Operand::Node(v) => Some(Cow::Borrowed(v)),
+                v => Some(Cow::Owned(v.into_owned())),
+            }),
+        }
+    }
+
+    fn eval_nodes_arg(
+        &self,
+        arg: &Expression,
+        json: &'a Value,
+        mode: FilterMode,
+    ) -> JsonPathResult<Vec<Cow<'a, Value>>> {
+        match arg {
+            Expression::JsonQuery(tokens) => Ok(self
+                .eval_nodes(json, tokens)?
+                .into_iter()
+                .map(Cow::Borrowed)
+                .collect()),
+            Expression::RootQuery { id, tokens } => Ok(self
+                .eval_root_nodes(json, *id, tokens)?
+                .iter()
+                .map(|v| Cow::Borrowed(*v))
+                .collect()),
+            Expression::Function(f) => match self.eval_function(f, json, mode)? {
+                FunctionValue::Nodes(nodes) => Ok(nodes),
+                _ => Ok(vec![]),
+            },
+            _ => Ok(vec![]),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use serde_json::Value;
+
+use crate::{tokenizer::Token, JsonPathError, JsonPathResult};
+
+use super::{children, Eval, Selection};
+
+/// A lazy iterator over the nodes selected by a jsonpath, in the order they are selected.
+///
+/// Nodes are selected on demand, e.g. `.next()` stops walking the json document as soon as
+/// the first node is found. The iterator ends after returning an evaluation error.
+pub struct Iter<'a, 't> {
+    eval: Eval<'a, 't>,
+    tokens: &'t [Token],
+    /// pending work in reverse order, the next step is at the top of the stack
+    stack: Vec<Step<'a>>,
+}
+
+enum Step<'a> {
+    /// apply the token at the index to the node, the node is selected after the last token
+    Visit(&'a Value, usize),
+    /// apply the token at the index to the node and all of its descendants, for `..`
+    Walk(&'a Value, usize),
+    /// test the child of a node with the filter at the index, the child is visited with the
+    /// next token if the filter is true
+    Test(&'a Value, usize),
+    Error(JsonPathError),
+}
+
+impl<'a, 't> Iter<'a, 't> {
+    pub(super) fn new(mut eval: Eval<'a, 't>, json: &'a Value, tokens: &'t [Token]) -> Self {
+        eval.start(json);
+        let step = match tokens.first() {
+            Some(Token::Root(_)) => Step::Visit(json, 1),
+            None => Step::Error(JsonPathError::EvaluationError(
+                "Empty jsonpath provided".to_string(),
+            )),
+            Some(_) => Step::Error(JsonPathError::EvaluationError(
+                "Invalid start token for the given jsonpath".to_string(),
+            )),
+        };
+        Iter {
+            eval,
+            tokens,
+            stack: vec![step],
+        }
+    }
+
+    /// An iterator that only returns the given error.
+    pub(crate) fn error(e: JsonPathError) -> Self {
+        Iter {
+            eval: Eval::new(),
+            tokens: &[],
+            stack: vec![Step::Error(e)],
+        }
+    }
+
+    /// push the steps for the nodes selected by the token at the index
+    fn visit(&mut self, json: &'a Value, index: usize) -> JsonPathResult<()> {
+        let next = index + 1;
+        if let Token::Filter(_) = self.tokens[index] {
+            // each child is only tested when it is the next step
+            self.stack.extend(
+                children(json)
+                    .into_iter()
+                    .rev()
+                    .map(|(_, child)| Step::Test(child, index)),
+            );
+            return Ok(());
+        }
+        match self.eval.select(&self.tokens[index], json)? {
+            // the first selected node is at the top of the stack
+            Selection::Nodes(nodes) => self.stack.extend(
+                nodes
+                    .into_iter()
+                    .rev()
+                    .map(|(_, node)| Step::Visit(node, next)),
+            ),
+            // a trailing scan selects nothing
+            Selection::Descendants if next < self.tokens.len() => {
+                self.stack.push(Step::Walk(json, next))
+            }
+            Selection::Descendants => {}
+        }
+        Ok(())
+    }
+
+    /// push the step for the child if the filter at the index is true
+    fn test(&mut self, child: &'a Value, index: usize) -> JsonPathResult<()> {
+        if let Token::Filter(expression) = &self.tokens[index] {
+            if self.eval.eval_test(expression, child)? {
+                self.stack.push(Step::Visit(child, index + 1));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Iter<'a, '_> {
+    type Item = JsonPathResult<&'a Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.stack.pop() {
+            let result = match step {
+                Step::Visit(json, index) if index == self.tokens.len() => return Some(Ok(json)),
+                Step::Visit(json, index) => self.visit(json, index),
+                Step::Walk(json, index) => {
+                    self.stack.extend(
+                        children(json)
+                            .into_iter()
+                            .rev()
+                            .map(|(_, child)| Step::Walk(child, index)),
+                    );
+                    // the node itself is visited before its descendants
+                    self.stack.push(Step::Visit(json, index));
+                    Ok(())
+                }
+                Step::Test(child, index) => self.test(child, index),
+                Step::Error(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.stack.clear();
+                return Some(Err(e));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        tokenizer::{FilterMode, Tokenizer},
+        Bindings, FunctionRegistry, FunctionType, JsonPathResult,
+    };
+
+    use super::Eval;
+
+    fn iter_and_eval<'a>(
+        json: &'a Value,
+        bindings: &'a Bindings,
+        path: &str,
+        filter_mode: FilterMode,
+    ) -> (JsonPathResult<Vec<&'a Value>>, Vec<&'a Value>) {
+        let tokens = Tokenizer::new()
+            .with_filter_mode(filter_mode)
+            .tokenize(path)
+            .unwrap();
+        let nodes = Eval::with_bindings(bindings)
+            .iter(json, &tokens)
+            .collect::<JsonPathResult<Vec<_>>>();
+        let expected = Eval::with_bindings(bindings)
+            .eval_ref(json, &tokens)
+            .unwrap();
+        (nodes, expected)
+    }
+
+    #[test]
+    fn iter_selects_the_same_nodes_as_eval() {
+        let json = json!({
+            "a": [{"b": 1, "c": [1, 2]}, {"b": 2}, {"b": 3, "c": []}],
+            "b": {"b": "x"},
+        });
+        let bindings = Bindings::new().bind("key", "b").bind("index", 1);
+        for path in [
+            "$",
+            "$.a",
+            "$.a[0].b",
+            "$.a[-1]",
+            "$.a[0, 2, 0].b",
+            "$.a[1:]",
+            "$.a[:-1].b",
+            "$.a[0:2]['b', 'c']",
+            "$.a[*][?(@.b > 1)].b",
+            "$.a[*][?(@.c)]",
+            "$..b",
+            "$..c[*]",
+            "$..*",
+            "$.a..*",
+            "$..['b', 'c']",
+            "$.*",
+            "$[$key][$key]",
+            "$.a[$index]",
+            "$.a[*][?@.b > 1]",
+            "$.a[?@.b > 1].b",
+            "$.a[-1, *, 'b', ?@.c].b",
+            "$['b', $key, ?@.a].b",
+            "$.a[2, 0:2, *].b",
+            "$.a[::-1, 'b', 1]['c', 'b']",
+            "$.a[*][?(@.b < $.a[2].b)]",
+            "$.a[*][?(count(@.c[*]) < count($.a[0].c[*]))].b",
+        ] {
+            for filter_mode in [FilterMode::Standard, FilterMode::Compat] {
+                let (nodes, expected) = iter_and_eval(&json, &bindings, path, filter_mode);
+                assert_eq!(Ok(expected), nodes, "{} {:?}", path, filter_mode);
+            }
+        }
+    }
+
+    #[test]
+    fn iter_selects_nodes_on_demand() {
+        let json = json!({"a": [1, "x"], "b": 2});
+        let tokens = Tokenizer::new().tokenize("$.a[*]").unwrap();
+        let mut iter = Eval::new().iter(&json, &tokens);
+        assert_eq!(Some(Ok(&json!(1))), iter.next());
+        assert_eq!(Some(Ok(&json!("x"))), iter.next());
+        assert_eq!(None, iter.next());
+
+        // errors end the iteration
+        let tokens = Tokenizer::new().tokenize("$.*[0]").unwrap();
+        let mut iter = Eval::new().iter(&json, &tokens);
+        assert_eq!(Some(Ok(&json!(1))), iter.next());
+        assert!(matches!(iter.next(), Some(Err(_))));
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn iter_tests_filters_on_demand() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut functions = FunctionRegistry::new();
+        functions
+            .register(
+                "counted",
+                vec![FunctionType::ValueType],
+                FunctionType::LogicalType,
+                move |args| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Value::Bool(args[0].as_i64().is_some_and(|n| n % 2 == 0))
+                },
+            )
+            .unwrap();
+        let tokens = Tokenizer::new()
+            .with_functions(functions)
+            .with_filter_mode(FilterMode::Standard)
+            .tokenize("$.a[?counted(@)]")
+            .unwrap();
+        let json = json!({"a": [1, 2, 3, 4, 5, 6]});
+        let mut iter = Eval::new().iter(&json, &tokens);
+        assert_eq!(Some(Ok(&json!(2))), iter.next());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(Some(Ok(&json!(4))), iter.next());
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+    }
+}
@@ -1,70 +1,67 @@
+use std::borrow::Cow;
+
 use serde_json::Value;
 
 use crate::{JsonPathError, JsonPathResult};
 
-pub trait ResultAcceptor {
-    fn accept(&mut self, result: Option<Value>) -> JsonPathResult<()>;
-    fn result(&mut self) -> JsonPathResult<Value>;
-    fn is_scalar(&self) -> bool;
+/// Collects the results of an evaluation, values are borrowed from the json document and only
+/// copied when the final result is built.
+pub trait ResultAcceptor<'a> {
+    fn accept(&mut self, result: Cow<'a, Value>) -> JsonPathResult<()>;
+    /// The result of the evaluation, None if no node is selected for a scalar result.
+    fn result(&mut self) -> JsonPathResult<Option<Value>>;
 }
 
-pub struct ScalarResultAcceptor {
-    result: Option<Value>,
+pub struct ScalarResultAcceptor<'a> {
+    result: Option<Cow<'a, Value>>,
 }
 
-impl ScalarResultAcceptor {
+impl<'a> ScalarResultAcceptor<'a> {
     pub fn new() -> Self {
         Self { result: None }
     }
+
+    /// The selected node without copying it, None if no node is selected.
+    pub fn take(&mut self) -> Option<Cow<'a, Value>> {
+        self.result.take()
+    }
 }
 
-impl ResultAcceptor for ScalarResultAcceptor {
-    fn accept(&mut self, result: Option<Value>) -> JsonPathResult<()> {
+impl<'a> ResultAcceptor<'a> for ScalarResultAcceptor<'a> {
+    fn accept(&mut self, result: Cow<'a, Value>) -> JsonPathResult<()> {
         if self.result.is_some() {
             return Err(JsonPathError::EvaluationError(
                 "Invalid state, there is already a scalar result.".to_string(),
             ));
         }
-        self.result = result;
+        self.result = Some(result);
         Ok(())
     }
 
-    fn result(&mut self) -> JsonPathResult<Value> {
-        match self.result.take() {
-            Some(v) => Ok(v),
-            None => Ok(Value::Null),
-        }
-    }
-
-    fn is_scalar(&self) -> bool {
-        true
+    fn result(&mut self) -> JsonPathResult<Option<Value>> {
+        Ok(self.take().map(Cow::into_owned))
     }
 }
 
-pub struct ArrayResultRegister {
-    result: Vec<Value>,
+pub struct ArrayResultRegister<'a> {
+    result: Vec<Cow<'a, Value>>,
 }
 
-impl ArrayResultRegister {
+impl ArrayResultRegister<'_> {
     pub fn new() -> Self {
         Self { result: vec![] }
     }
 }
 
-impl ResultAcceptor for ArrayResultRegister {
-    fn accept(&mut self, result: Option<Value>) -> JsonPathResult<()> {
-        match result {
-            Some(r) => self.result.push(r),
-            None => {}
-        }
+impl<'a> ResultAcceptor<'a> for ArrayResultRegister<'a> {
+    fn accept(&mut self, result: Cow<'a, Value>) -> JsonPathResult<()> {
+        self.result.push(result);
         Ok(())
     }
 
-    fn result(&mut self) -> JsonPathResult<Value> {
-        Ok(Value::Array(self.result.clone()))
-    }
-
-    fn is_scalar(&self) -> bool {
-        false
+    fn result(&mut self) -> JsonPathResult<Option<Value>> {
+        Ok(Some(Value::Array(
+            self.result.drain(..).map(Cow::into_owned).collect(),
+        )))
     }
 }
@@ -0,0 +1,31 @@
+use std::ops::ControlFlow;
+
+use serde_json::Value;
+
+use crate::NormalizedPath;
+
+/// Receives the nodes selected by [`super::Eval::eval_into`], in the order they are selected.
+///
+/// Unlike [`super::Eval::eval`], properties selected by `['a','b']` are passed as separate
+/// nodes instead of being merged into one object.
+pub trait ResultSink<'a> {
+    /// Accept a selected node and its location, return `ControlFlow::Break(())` to stop the
+    /// evaluation.
+    fn accept(&mut self, path: &NormalizedPath<'a>, value: &'a Value) -> ControlFlow<()>;
+}
+
+/// Collects all selected nodes.
+impl<'a> ResultSink<'a> for Vec<&'a Value> {
+    fn accept(&mut self, _path: &NormalizedPath<'a>, value: &'a Value) -> ControlFlow<()> {
+        self.push(value);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Collects all selected nodes along with their locations.
+impl<'a> ResultSink<'a> for Vec<(NormalizedPath<'a>, &'a Value)> {
+    fn accept(&mut self, path: &NormalizedPath<'a>, value: &'a Value) -> ControlFlow<()> {
+        self.push((path.clone(), value));
+        ControlFlow::Continue(())
+    }
+}
@@ -1,10 +1,16 @@
+mod bindings;
 mod errors;
 pub mod eval;
+mod normalized_path;
+mod path;
 pub mod tokenizer;
+pub use bindings::Bindings;
 pub use errors::*;
-use eval::Eval;
+pub use eval::{Collation, ResultSink};
+pub use normalized_path::{NormalizedPath, PathElement};
+pub use path::JsonPath;
 use serde_json::Value;
-use tokenizer::Tokenizer;
+pub use tokenizer::{FilterMode, FunctionRegistry, FunctionType, ResultShape};
 
 pub trait JsonPathQuery {
     fn query(&self, json_path: &str) -> JsonPathResult<Value>;
@@ -12,9 +18,6 @@ pub trait JsonPathQuery {
 
 impl JsonPathQuery for Value {
     fn query(&self, json_path: &str) -> JsonPathResult<Value> {
-        let tz = Tokenizer::new();
-        let tokens = tz.tokenize(json_path)?;
-        let mut eval = Eval::new();
-        eval.eval(self, tokens)
+        JsonPath::parse(json_path)?.query(self)
     }
 }
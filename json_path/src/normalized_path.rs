@@ -0,0 +1,205 @@
+use std::{borrow::Cow, fmt::Display};
+
+use serde_json::Value;
+
+/// The location of a node within a json document, e.g. `$['store']['book'][0]['title']`.
+///
+/// See RFC 9535 section 2.7. Names are borrowed from the document when possible.
+///
+/// Paths are ordered element by element, a path is ordered before its descendants, and array
+/// indices are ordered numerically.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NormalizedPath<'a> {
+    elements: Vec<PathElement<'a>>,
+}
+
+/// A member name or an array index within a [`NormalizedPath`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathElement<'a> {
+    Name(Cow<'a, str>),
+    Index(usize),
+}
+
+impl<'a> NormalizedPath<'a> {
+    /// The path of the root node, i.e. `$`.
+    pub fn new() -> NormalizedPath<'a> {
+        NormalizedPath { elements: vec![] }
+    }
+
+    pub fn elements(&self) -> &[PathElement<'a>] {
+        &self.elements
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn push(&mut self, element: PathElement<'a>) {
+        self.elements.push(element);
+    }
+
+    pub fn pop(&mut self) -> Option<PathElement<'a>> {
+        self.elements.pop()
+    }
+
+    /// Copy borrowed names, so that the path no longer borrows the json document.
+    pub fn into_owned(self) -> NormalizedPath<'static> {
+        let elements = self
+            .elements
+            .into_iter()
+            .map(|e| match e {
+                PathElement::Name(name) => PathElement::Name(Cow::Owned(name.into_owned())),
+                PathElement::Index(index) => PathElement::Index(index),
+            })
+            .collect();
+        NormalizedPath { elements }
+    }
+
+    /// Get the node at this location of the given json document.
+    pub fn resolve<'v>(&self, json: &'v Value) -> Option<&'v Value> {
+        self.elements
+            .iter()
+            .try_fold(json, |json, element| match (element, json) {
+                (PathElement::Name(name), Value::Object(object)) => object.get(name.as_ref()),
+                (PathElement::Index(index), Value::Array(array)) => array.get(*index),
+                _ => None,
+            })
+    }
+
+    /// Get the mutable node at this location of the given json document.
+    pub fn resolve_mut<'v>(&self, json: &'v mut Value) -> Option<&'v mut Value> {
+        self.elements
+            .iter()
+            .try_fold(json, |json, element| match (element, json) {
+                (PathElement::Name(name), Value::Object(object)) => object.get_mut(name.as_ref()),
+                (PathElement::Index(index), Value::Array(array)) => array.get_mut(*index),
+                _ => None,
+            })
+    }
+
+    /// Remove the node at this location from its parent, the root node can not be removed.
+    pub fn remove(&self, json: &mut Value) -> Option<Value> {
+        let (last, parent) = self.elements.split_last()?;
+        let parent = NormalizedPath {
+            elements: parent.to_vec(),
+        };
+        match (last, parent.resolve_mut(json)?) {
+            (PathElement::Name(name), Value::Object(object)) => object.remove(name.as_ref()),
+            (PathElement::Index(index), Value::Array(array)) if *index < array.len() => {
+                Some(array.remove(*index))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromIterator<PathElement<'a>> for NormalizedPath<'a> {
+    fn from_iter<T: IntoIterator<Item = PathElement<'a>>>(iter: T) -> Self {
+        NormalizedPath {
+            elements: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Display for NormalizedPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("$")?;
+        for element in self.elements.iter() {
+            element.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PathElement<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathElement::Index(index) => write!(f, "[{}]", index),
+            PathElement::Name(name) => {
+                f.write_str("['")?;
+                for c in name.chars() {
+                    match c {
+                        '\'' => f.write_str("\\'")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\u{0008}' => f.write_str("\\b")?,
+                        '\u{000C}' => f.write_str("\\f")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\t' => f.write_str("\\t")?,
+                        c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                f.write_str("']")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use serde_json::json;
+
+    use super::{NormalizedPath, PathElement};
+
+    #[test]
+    fn normalized_path_can_be_displayed() {
+        let path: NormalizedPath = vec![
+            PathElement::Name(Cow::Borrowed("store")),
+            PathElement::Name(Cow::Borrowed("it's\n")),
+            PathElement::Index(0),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!("$['store']['it\\'s\\n'][0]", path.to_string());
+        assert_eq!("$", NormalizedPath::new().to_string());
+    }
+
+    #[test]
+    fn normalized_path_can_be_resolved() {
+        let json = json!({"store": {"book": [{"title": "a"}]}});
+        let path: NormalizedPath = vec![
+            PathElement::Name(Cow::Borrowed("store")),
+            PathElement::Name(Cow::Borrowed("book")),
+            PathElement::Index(0),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(Some(&json!({"title": "a"})), path.resolve(&json));
+        assert_eq!(Some(&json), NormalizedPath::new().resolve(&json));
+        let mut path = path.into_owned();
+        path.push(PathElement::Index(0));
+        assert_eq!(None, path.resolve(&json));
+    }
+
+    #[test]
+    fn normalized_path_can_be_mutated() {
+        let mut json = json!({"store": {"book": [{"title": "a"}, {"title": "b"}]}});
+        let path: NormalizedPath = vec![
+            PathElement::Name(Cow::Borrowed("store")),
+            PathElement::Name(Cow::Borrowed("book")),
+            PathElement::Index(0),
+        ]
+        .into_iter()
+        .collect();
+        *path.resolve_mut(&mut json).unwrap() = json!("c");
+        assert_eq!(Some(json!("c")), path.remove(&mut json));
+        assert_eq!(json!({"store": {"book": [{"title": "b"}]}}), json);
+        assert_eq!(None, NormalizedPath::new().remove(&mut json));
+    }
+
+    #[test]
+    fn normalized_paths_are_ordered() {
+        let path = |elements: Vec<PathElement<'static>>| -> NormalizedPath<'static> {
+            elements.into_iter().collect()
+        };
+        assert!(path(vec![PathElement::Index(2)]) < path(vec![PathElement::Index(10)]));
+        assert!(
+            path(vec![PathElement::Index(2)])
+                < path(vec![PathElement::Index(2), PathElement::Index(0)])
+        );
+        assert!(NormalizedPath::new() < path(vec![PathElement::Name(Cow::Borrowed("a"))]));
+    }
+}
@@ -0,0 +1,1011 @@
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    eval::{Collation, Eval, Iter, ResultSink},
+    tokenizer::{
+        self, Expression, PlaceholderToken, PropertyPathToken, ResultShape, Selector, Token,
+        Tokenizer,
+    },
+    Bindings, JsonPathError, JsonPathResult, NormalizedPath, PathElement,
+};
+
+/// A compiled jsonpath.
+///
+/// The jsonpath string is tokenized once by [`JsonPath::parse`], the result can then be
+/// evaluated against any number of json documents, and shared across threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    path: String,
+    tokens: Vec<Token>,
+    placeholders: Vec<PlaceholderToken>,
+    omit_missing: bool,
+    collation: Collation,
+}
+
+impl JsonPath {
+    /// Parse and compile the given jsonpath string.
+    pub fn parse(json_path: &str) -> JsonPathResult<JsonPath> {
+        Self::parse_with(json_path, &Tokenizer::new())
+    }
+
+    /// Parse and compile the given jsonpath string with a configured tokenizer, e.g. one that
+    /// knows about user defined functions.
+    pub fn parse_with(json_path: &str, tokenizer: &Tokenizer) -> JsonPathResult<JsonPath> {
+        let tokens = tokenizer.tokenize(json_path)?;
+        let mut placeholders = vec![];
+        collect_placeholders(&tokens, &mut placeholders);
+        Ok(JsonPath {
+            path: json_path.to_string(),
+            tokens,
+            placeholders,
+            omit_missing: false,
+            collation: Collation::default(),
+        })
+    }
+
+    /// Evaluate the jsonpath against the given json document.
+    pub fn query(&self, json: &Value) -> JsonPathResult<Value> {
+        self.query_with(json, &Bindings::new())
+    }
+
+    /// Evaluate the jsonpath against the given json document, with values bound to its
+    /// placeholders. All placeholders must be bound, with values of the expected types.
+    pub fn query_with(&self, json: &Value, bindings: &Bindings) -> JsonPathResult<Value> {
+        self.check_bindings(bindings)?;
+        self.eval(bindings).eval(json, &self.tokens)
+    }
+
+    /// Same as [`JsonPath::query`], but returns `None` instead of `null` when the jsonpath
+    /// should return a scalar result and no node is selected, e.g. `$.a` against `{}`.
+    pub fn query_opt(&self, json: &Value) -> JsonPathResult<Option<Value>> {
+        self.query_opt_with(json, &Bindings::new())
+    }
+
+    /// Same as [`JsonPath::query_opt`], with values bound to placeholders.
+    pub fn query_opt_with(
+        &self,
+        json: &Value,
+        bindings: &Bindings,
+    ) -> JsonPathResult<Option<Value>> {
+        self.check_bindings(bindings)?;
+        self.eval(bindings).eval_opt(json, &self.tokens)
+    }
+
+    /// Omit missing properties from the object returned for `['a','b']`, instead of setting
+    /// them to `null`.
+    pub fn with_omit_missing(mut self, omit_missing: bool) -> Self {
+        self.omit_missing = omit_missing;
+        self
+    }
+
+    /// Compare strings within filters with the given collation, e.g.
+    /// [`Collation::CaseInsensitive`] for `[?(@.name >= 'm')]` to also select `Max`.
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    fn eval<'a, 'b>(&self, bindings: &'b Bindings) -> Eval<'a, 'b> {
+        self.configure(Eval::with_bindings(bindings))
+    }
+
+    fn configure<'a, 'b>(&self, eval: Eval<'a, 'b>) -> Eval<'a, 'b> {
+        eval.with_omit_missing(self.omit_missing)
+            .with_collation(self.collation)
+    }
+
+    /// Evaluate the jsonpath against the given json document, and return references to the
+    /// selected nodes without copying any of them.
+    ///
+    /// Unlike [`JsonPath::query`], properties selected by `['a','b']` are returned as separate
+    /// nodes instead of being merged into one object.
+    pub fn query_ref<'a>(&self, json: &'a Value) -> JsonPathResult<Vec<&'a Value>> {
+        self.query_ref_with(json, &Bindings::new())
+    }
+
+    /// Same as [`JsonPath::query_ref`], with values bound to placeholders.
+    pub fn query_ref_with<'a>(
+        &self,
+        json: &'a Value,
+        bindings: &Bindings,
+    ) -> JsonPathResult<Vec<&'a Value>> {
+        self.check_bindings(bindings)?;
+        self.eval(bindings).eval_ref(json, &self.tokens)
+    }
+
+    /// Evaluate the jsonpath lazily against the given json document, nodes are selected on
+    /// demand in the same order as [`JsonPath::query_ref`].
+    pub fn iter<'a, 't>(&'t self, json: &'a Value) -> Iter<'a, 't> {
+        match self.check_bindings(&Bindings::new()) {
+            Ok(()) => self.configure(Eval::new()).iter(json, &self.tokens),
+            Err(e) => Iter::error(e),
+        }
+    }
+
+    /// Same as [`JsonPath::iter`], with values bound to placeholders. Missing bindings are
+    /// returned as the first item of the iterator.
+    pub fn iter_with<'a, 't>(&'t self, json: &'a Value, bindings: &'t Bindings) -> Iter<'a, 't> {
+        match self.check_bindings(bindings) {
+            Ok(()) => self.eval(bindings).iter(json, &self.tokens),
+            Err(e) => Iter::error(e),
+        }
+    }
+
+    /// Evaluate the jsonpath against the given json document, and pass every selected node to
+    /// the sink as soon as it is selected, until the sink stops the evaluation.
+    pub fn query_into<'a>(
+        &self,
+        json: &'a Value,
+        sink: &mut impl ResultSink<'a>,
+    ) -> JsonPathResult<()> {
+        self.query_into_with(json, &Bindings::new(), sink)
+    }
+
+    /// Same as [`JsonPath::query_into`], with values bound to placeholders.
+    pub fn query_into_with<'a>(
+        &self,
+        json: &'a Value,
+        bindings: &Bindings,
+        sink: &mut impl ResultSink<'a>,
+    ) -> JsonPathResult<()> {
+        self.check_bindings(bindings)?;
+        self.eval(bindings).eval_into(json, &self.tokens, sink)
+    }
+
+    /// Evaluate the jsonpath against the given json document, and return the selected nodes
+    /// along with their locations, e.g. `$['store']['book'][0]['title']`.
+    ///
+    /// Unlike [`JsonPath::query`], properties selected by `['a','b']` are returned as separate
+    /// nodes instead of being merged into one object.
+    pub fn query_located<'a>(
+        &self,
+        json: &'a Value,
+    ) -> JsonPathResult<Vec<(NormalizedPath<'a>, &'a Value)>> {
+        self.query_located_with(json, &Bindings::new())
+    }
+
+    /// Same as [`JsonPath::query_located`], with values bound to placeholders.
+    pub fn query_located_with<'a>(
+        &self,
+        json: &'a Value,
+        bindings: &Bindings,
+    ) -> JsonPathResult<Vec<(NormalizedPath<'a>, &'a Value)>> {
+        self.check_bindings(bindings)?;
+        self.eval(bindings).eval_located(json, &self.tokens)
+    }
+
+    /// Replace every node selected by the jsonpath with the result of the given function, a node
+    /// is removed from its parent when the function returns `None`. Returns the number of the
+    /// selected nodes.
+    ///
+    /// Descendants are replaced before their ancestors, and the root node is replaced by `null`
+    /// when it is removed.
+    pub fn update<F>(&self, json: &mut Value, f: F) -> JsonPathResult<usize>
+    where
+        F: FnMut(Value) -> Option<Value>,
+    {
+        self.update_with(json, &Bindings::new(), f)
+    }
+
+    /// Same as [`JsonPath::update`], with values bound to placeholders.
+    pub fn update_with<F>(
+        &self,
+        json: &mut Value,
+        bindings: &Bindings,
+        mut f: F,
+    ) -> JsonPathResult<usize>
+    where
+        F: FnMut(Value) -> Option<Value>,
+    {
+        let paths = self.locate(json, bindings)?;
+        for path in paths.iter() {
+            if let Some(node) = path.resolve_mut(json) {
+                match f(std::mem::take(node)) {
+                    Some(value) => *node = value,
+                    None => {
+                        path.remove(json);
+                    }
+                }
+            }
+        }
+        Ok(paths.len())
+    }
+
+    /// Same as [`JsonPath::update`].
+    #[deprecated(
+        note = "use `JsonPath::update`, `replace_with` does not follow the `<op>_with` naming of the methods with bindings"
+    )]
+    pub fn replace_with<F>(&self, json: &mut Value, f: F) -> JsonPathResult<usize>
+    where
+        F: FnMut(Value) -> Option<Value>,
+    {
+        self.update(json, f)
+    }
+
+    /// Set every node selected by the jsonpath to the given value. Returns the number of the
+    /// selected nodes.
+    pub fn set(&self, json: &mut Value, value: Value) -> JsonPathResult<usize> {
+        self.set_with(json, &Bindings::new(), value)
+    }
+
+    /// Same as [`JsonPath::set`], with values bound to placeholders.
+    pub fn set_with(
+        &self,
+        json: &mut Value,
+        bindings: &Bindings,
+        value: Value,
+    ) -> JsonPathResult<usize> {
+        self.update_with(json, bindings, |_| Some(value.clone()))
+    }
+
+    /// Remove every node selected by the jsonpath from its parent object or array. Returns the
+    /// number of the removed nodes, the root node is never removed.
+    pub fn delete(&self, json: &mut Value) -> JsonPathResult<usize> {
+        self.delete_with(json, &Bindings::new())
+    }
+
+    /// Same as [`JsonPath::delete`], with values bound to placeholders.
+    pub fn delete_with(&self, json: &mut Value, bindings: &Bindings) -> JsonPathResult<usize> {
+        let paths = self.locate(json, bindings)?;
+        Ok(paths.iter().filter_map(|path| path.remove(json)).count())
+    }
+
+    /// Write the value at the location of a definite jsonpath, which only consists of single
+    /// property names and array indices, e.g. `$.config.db.replicas[0].host`. Missing or `null`
+    /// intermediate nodes are created as objects or arrays, and an array index equal to the
+    /// length of the array appends to it. Returns the previous value at the location, if any.
+    ///
+    /// The json document is left unchanged when the value can not be inserted.
+    pub fn insert(&self, json: &mut Value, value: Value) -> JsonPathResult<Option<Value>> {
+        self.insert_with(json, &Bindings::new(), value)
+    }
+
+    /// Same as [`JsonPath::insert`], with values bound to placeholders, e.g. `$.users[$id]`.
+    pub fn insert_with(
+        &self,
+        json: &mut Value,
+        bindings: &Bindings,
+        value: Value,
+    ) -> JsonPathResult<Option<Value>> {
+        self.check_bindings(bindings)?;
+        let (location, missing) = self.insert_location(json, bindings)?;
+        let mut node = json;
+        for element in location.elements() {
+            node = match element {
+                PathElement::Name(name) => {
+                    if node.is_null() {
+                        *node = Value::Object(Map::new());
+                    }
+                    match node {
+                        Value::Object(object) => object.entry(name.as_ref()).or_insert(Value::Null),
+                        _ => unreachable!(),
+                    }
+                }
+                PathElement::Index(index) => {
+                    if node.is_null() {
+                        *node = Value::Array(vec![]);
+                    }
+                    match node {
+                        Value::Array(array) => {
+                            if *index == array.len() {
+                                array.push(Value::Null);
+                            }
+                            &mut array[*index]
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            };
+        }
+        let previous = std::mem::replace(node, value);
+        Ok(if missing { None } else { Some(previous) })
+    }
+
+    /// The location to insert at, and whether there is no node at it yet. Checks the whole
+    /// jsonpath before the document is changed.
+    fn insert_location<'p>(
+        &'p self,
+        json: &Value,
+        bindings: &'p Bindings,
+    ) -> JsonPathResult<(NormalizedPath<'p>, bool)> {
+        if !self.is_definite() {
+            return Err(JsonPathError::EvaluationError(format!(
+                "Can not insert into an indefinite jsonpath: {}",
+                self.path
+            )));
+        }
+
+        let mut location = NormalizedPath::new();
+        // None below a missing node, which will be created
+        let mut node = Some(json);
+        for token in self.tokens.iter() {
+            let key = match token {
+                Token::Root(_) => continue,
+                Token::Property(PropertyPathToken { properties }) => {
+                    InsertKey::Name(&properties[0])
+                }
+                Token::ArrayIndex { indices } => InsertKey::Index(indices[0]),
+                Token::Placeholder(placeholder) => match bindings.resolve(placeholder)? {
+                    Value::String(name) => InsertKey::Name(name),
+                    Value::Number(n) => InsertKey::Index(
+                        n.as_i64()
+                            .map_or(i32::MAX, |i| i32::try_from(i).unwrap_or(i32::MAX)),
+                    ),
+                    other => {
+                        return Err(JsonPathError::EvaluationError(format!(
+                            "Can not insert at placeholder ${}, expect a name or an index: {}",
+                            placeholder.name, other
+                        )))
+                    }
+                },
+                _ => unreachable!(),
+            };
+            match key {
+                InsertKey::Name(prop) => {
+                    node = match node {
+                        None | Some(Value::Null) => None,
+                        Some(Value::Object(object)) => object.get(prop),
+                        Some(other) => {
+                            return Err(JsonPathError::EvaluationError(format!(
+                                "Can not insert property '{}' into a non-object value: {}",
+                                prop, other
+                            )))
+                        }
+                    };
+                    location.push(PathElement::Name(Cow::Borrowed(prop)));
+                }
+                InsertKey::Index(index) => {
+                    let array: &[Value] = match node {
+                        None | Some(Value::Null) => &[],
+                        Some(Value::Array(array)) => array,
+                        Some(other) => {
+                            return Err(JsonPathError::EvaluationError(format!(
+                                "Can not insert array index {} into a non-array value: {}",
+                                index, other
+                            )))
+                        }
+                    };
+                    let index = insert_index(index, array.len())?;
+                    node = array.get(index);
+                    location.push(PathElement::Index(index));
+                }
+            }
+        }
+        Ok((location, node.is_none()))
+    }
+
+    /// The distinct locations of the selected nodes, in the reverse document order so that
+    /// descendants and higher array indices come first, and can be modified without
+    /// invalidating the other locations.
+    fn locate(
+        &self,
+        json: &Value,
+        bindings: &Bindings,
+    ) -> JsonPathResult<Vec<NormalizedPath<'static>>> {
+        let mut paths = self
+            .query_located_with(json, bindings)?
+            .into_iter()
+            .map(|(path, _)| path.into_owned())
+            .collect::<Vec<_>>();
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn check_bindings(&self, bindings: &Bindings) -> JsonPathResult<()> {
+        for placeholder in self.placeholders.iter() {
+            bindings.resolve(placeholder)?;
+        }
+        Ok(())
+    }
+
+    /// Whether this jsonpath selects at most one node, i.e. it only consists of single property
+    /// names, array indices and placeholders.
+    pub fn is_definite(&self) -> bool {
+        tokenizer::is_definite(&self.tokens)
+    }
+
+    /// The shape of the result returned by [`JsonPath::query`], for any json document.
+    pub fn result_shape(&self) -> ResultShape {
+        tokenizer::result_shape(&self.tokens)
+    }
+
+    /// The named placeholders within this jsonpath.
+    pub fn placeholders(&self) -> &[PlaceholderToken] {
+        &self.placeholders
+    }
+
+    /// The original jsonpath string.
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    /// The compiled tokens of this jsonpath.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+}
+
+/// a property name or an array index on the way to the location of [`JsonPath::insert`]
+enum InsertKey<'p> {
+    Name(&'p str),
+    Index(i32),
+}
+
+/// the position of an array index to insert at, an index equal to the length appends
+fn insert_index(index: i32, len: usize) -> JsonPathResult<usize> {
+    let position = if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)
+    } else {
+        Some(index as usize).filter(|i| *i <= len)
+    };
+    position.ok_or_else(|| {
+        JsonPathError::EvaluationError(format!(
+            "Array index {} is out of bounds, the array has {} items.",
+            index, len
+        ))
+    })
+}
+
+fn collect_placeholders(tokens: &[Token], placeholders: &mut Vec<PlaceholderToken>) {
+    for token in tokens {
+        match token {
+            Token::Placeholder(placeholder) => add_placeholder(placeholder, placeholders),
+            Token::Predicate(expression) | Token::Filter(expression) => {
+                collect_expr_placeholders(expression, placeholders)
+            }
+            Token::Union(selectors) => {
+                for selector in selectors {
+                    match selector {
+                        Selector::Placeholder(placeholder) => {
+                            add_placeholder(placeholder, placeholders)
+                        }
+                        Selector::Filter(expression) | Selector::Predicate(expression) => {
+                            collect_expr_placeholders(expression, placeholders)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_expr_placeholders(expression: &Expression, placeholders: &mut Vec<PlaceholderToken>) {
+    match expression {
+        Expression::Placeholder(placeholder) => add_placeholder(placeholder, placeholders),
+        Expression::JsonQuery(tokens) | Expression::RootQuery { tokens, .. } => {
+            collect_placeholders(tokens, placeholders)
+        }
+        Expression::Not(inner) | Expression::Negate(inner) => {
+            collect_expr_placeholders(inner, placeholders)
+        }
+        Expression::Array(expressions) => expressions
+            .iter()
+            .for_each(|e| collect_expr_placeholders(e, placeholders)),
+        Expression::Function(function) => function
+            .args
+            .iter()
+            .for_each(|e| collect_expr_placeholders(e, placeholders)),
+        Expression::CompareExpr { left, right, .. } => {
+            collect_expr_placeholders(left, placeholders);
+            collect_expr_placeholders(right, placeholders);
+        }
+        Expression::Literal(_) | Expression::Regex(_) => {}
+    }
+}
+
+fn add_placeholder(placeholder: &PlaceholderToken, placeholders: &mut Vec<PlaceholderToken>) {
+    if !placeholders.contains(placeholder) {
+        placeholders.push(placeholder.clone());
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(s)
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use std::ops::ControlFlow;
+
+    use crate::{
+        eval::{Collation, ResultSink},
+        tokenizer::{FilterMode, Tokenizer},
+        Bindings, JsonPathError, JsonPathResult, NormalizedPath, ResultShape,
+    };
+
+    use super::JsonPath;
+
+    #[test]
+    fn json_path_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<JsonPath>();
+    }
+
+    #[test]
+    fn json_path_can_be_reused() {
+        let path = JsonPath::parse("$.data.msg").unwrap();
+        assert_eq!(
+            Ok(json!("hello")),
+            path.query(&json!({"data": {"msg": "hello"}}))
+        );
+        assert_eq!(
+            Ok(json!("jsonpath")),
+            path.query(&json!({"data": {"msg": "jsonpath"}}))
+        );
+    }
+
+    #[test]
+    fn json_path_reports_invalid_path() {
+        assert!(JsonPath::parse("data.msg").is_err());
+        assert!("$[1:2 3]".parse::<JsonPath>().is_err());
+    }
+
+    #[test]
+    fn json_path_supports_placeholders() {
+        let path = JsonPath::parse("$.users[*][?(@.id == $id || @.role in $roles)].name").unwrap();
+        let json = json!({"users": [
+            {"id": 1, "role": "admin", "name": "a"},
+            {"id": 2, "role": "user", "name": "b"},
+            {"id": 3, "role": "guest", "name": "c"},
+        ]});
+        let names = |bindings| path.query_with(&json, &bindings);
+        assert_eq!(
+            Ok(json!(["a", "c"])),
+            names(
+                Bindings::new()
+                    .bind("id", 1)
+                    .bind("roles", json!(["guest"]))
+            )
+        );
+        assert_eq!(
+            Ok(json!(["b"])),
+            names(Bindings::new().bind("id", 2).bind("roles", json!([])))
+        );
+        // values are never interpreted as jsonpath
+        assert_eq!(
+            Ok(json!([])),
+            names(
+                Bindings::new()
+                    .bind("id", "1 || true")
+                    .bind("roles", json!([]))
+            )
+        );
+    }
+
+    #[test]
+    fn json_path_reports_missing_or_invalid_bindings() {
+        let path = JsonPath::parse("$.users[*][?(@.id == $id && @.role in $roles)]").unwrap();
+        let json = json!({"users": []});
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Missing binding for placeholder $id".to_string()
+            )),
+            path.query(&json)
+        );
+        assert!(path
+            .query_with(&json, &Bindings::new().bind("id", 1).bind("roles", "admin"))
+            .is_err());
+    }
+
+    #[test]
+    fn json_path_supports_placeholder_selectors() {
+        let path = JsonPath::parse("$.data[$field][$index]").unwrap();
+        let json = json!({"data": {"items": ["a", "b"]}});
+        let bindings = Bindings::new().bind("field", "items").bind("index", -1);
+        assert_eq!(Ok(json!("b")), path.query_with(&json, &bindings));
+        let bindings = Bindings::new().bind("field", "items").bind("index", true);
+        assert!(path.query_with(&json, &bindings).is_err());
+    }
+
+    #[test]
+    fn json_path_can_modify_with_bindings() {
+        let mut json = json!({"users": {"a": {"roles": ["x"], "age": 1}, "b": {"age": 2}}});
+        let bindings = Bindings::new().bind("id", "a").bind("min", 2);
+        let path = JsonPath::parse("$.users[$id].age").unwrap();
+        assert_eq!(Ok(1), path.set_with(&mut json, &bindings, json!(3)));
+        assert!(path.set(&mut json, json!(3)).is_err());
+        let path = JsonPath::parse("$.users[*][?@.age >= $min].age").unwrap();
+        assert_eq!(
+            Ok(2),
+            path.update_with(&mut json, &bindings, |v| Some(json!([v])))
+        );
+        assert_eq!(
+            json!({"a": {"roles": ["x"], "age": [3]}, "b": {"age": [2]}}),
+            json["users"]
+        );
+        let path = JsonPath::parse("$.users[$id].roles").unwrap();
+        assert_eq!(Ok(1), path.delete_with(&mut json, &bindings));
+
+        let path = JsonPath::parse("$.users[$id].roles[$index]").unwrap();
+        let bindings = bindings.bind("id", "c").bind("index", 0);
+        assert_eq!(Ok(None), path.insert_with(&mut json, &bindings, json!("y")));
+        assert_eq!(json!({"roles": ["y"]}), json["users"]["c"]);
+        let bindings = bindings.bind("index", -2);
+        assert!(path.insert_with(&mut json, &bindings, json!("z")).is_err());
+        assert!(path.insert(&mut json, json!("z")).is_err());
+        assert_eq!(json!({"roles": ["y"]}), json["users"]["c"]);
+    }
+
+    #[test]
+    fn json_path_results_do_not_borrow_bindings() {
+        let json = json!({"items": [1, 2, 3]});
+        let path = JsonPath::parse("$.items[*][?@ > $min]").unwrap();
+        let nodes = {
+            let bindings = Bindings::new().bind("min", 1);
+            path.query_ref_with(&json, &bindings).unwrap()
+        };
+        assert_eq!(vec![&json!(2), &json!(3)], nodes);
+        let located = {
+            let bindings = Bindings::new().bind("min", 2);
+            path.query_located_with(&json, &bindings).unwrap()
+        };
+        assert_eq!("$['items'][2]", located[0].0.to_string());
+    }
+
+    #[test]
+    fn json_path_can_return_locations_of_results() {
+        let json =
+            json!({"store": {"book": [{"title": "a", "price": 8}, {"title": "b", "price": 12}]}});
+        let located = |path: &str| -> Vec<(String, serde_json::Value)> {
+            JsonPath::parse(path)
+                .unwrap()
+                .query_located(&json)
+                .unwrap()
+                .into_iter()
+                .map(|(p, v)| (p.to_string(), v.clone()))
+                .collect()
+        };
+        assert_eq!(
+            vec![("$['store']['book'][0]['title']".to_string(), json!("a"))],
+            located("$.store.book[0].title")
+        );
+        assert_eq!(
+            vec![
+                ("$['store']['book'][0]['title']".to_string(), json!("a")),
+                ("$['store']['book'][0]['price']".to_string(), json!(8))
+            ],
+            located("$.store.book[0]['title', 'price']")
+        );
+        assert_eq!(
+            vec![(
+                "$['store']['book'][1]".to_string(),
+                json!({"title": "b", "price": 12})
+            )],
+            located("$.store.book[*][?(@.price > 10)]")
+        );
+        assert_eq!(
+            vec![
+                ("$['store']['book'][0]['price']".to_string(), json!(8)),
+                ("$['store']['book'][1]['price']".to_string(), json!(12))
+            ],
+            located("$..price")
+        );
+        assert_eq!(vec![("$".to_string(), json.clone())], located("$"));
+        assert!(located("$.store.missing").is_empty());
+    }
+
+    #[test]
+    fn normalized_paths_can_be_parsed_back() {
+        let json = json!({"it's": {"a\nb": 1, "\\/\u{1}😀": 2}});
+        let located = JsonPath::parse("$[*][*]")
+            .unwrap()
+            .query_located(&json)
+            .unwrap();
+        assert_eq!(2, located.len());
+        for (location, value) in located {
+            let path = JsonPath::parse(&location.to_string()).unwrap();
+            assert_eq!(vec![value], path.query_ref(&json).unwrap());
+        }
+    }
+
+    #[test]
+    fn json_path_can_return_references_to_results() {
+        let json = json!({"data": [{"msg": "a", "n": 1}, {"msg": "b"}], "msg": "c"});
+        let path = JsonPath::parse("$..msg").unwrap();
+        let nodes = path.query_ref(&json).unwrap();
+        assert_eq!(vec![&json!("c"), &json!("a"), &json!("b")], nodes);
+        // the returned nodes are borrowed from the document
+        assert!(std::ptr::eq(nodes[0], &json["msg"]));
+
+        let path = JsonPath::parse("$.data[0]['msg', 'n', 'missing']").unwrap();
+        assert_eq!(vec![&json!("a"), &json!(1)], path.query_ref(&json).unwrap());
+        let path = JsonPath::parse("$.missing").unwrap();
+        assert!(path.query_ref(&json).unwrap().is_empty());
+
+        let path = JsonPath::parse("$.data[*][?(@.msg == $msg)]").unwrap();
+        let bindings = Bindings::new().bind("msg", "b");
+        assert_eq!(
+            vec![&json["data"][1]],
+            path.query_ref_with(&json, &bindings).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_path_can_set_values() {
+        let mut json = json!({"items": [
+            {"status": "stale"}, {"status": "fresh"}, {"status": "stale"}
+        ]});
+        let path = JsonPath::parse("$.items[*][?(@.status == 'stale')].status").unwrap();
+        assert_eq!(Ok(2), path.set(&mut json, json!("expired")));
+        assert_eq!(
+            json!({"items": [
+                {"status": "expired"}, {"status": "fresh"}, {"status": "expired"}
+            ]}),
+            json
+        );
+
+        let path = JsonPath::parse("$.missing").unwrap();
+        assert_eq!(Ok(0), path.set(&mut json, json!(1)));
+        let path = JsonPath::parse("$").unwrap();
+        assert_eq!(Ok(1), path.set(&mut json, json!(1)));
+        assert_eq!(json!(1), json);
+    }
+
+    #[test]
+    fn json_path_can_update_or_remove_values() {
+        let mut json = json!({"a": [1, 2, 3, 4, 5], "b": {"c": 1, "d": 2}});
+        let path = JsonPath::parse("$.a[0:4]").unwrap();
+        let replaced = path.update(&mut json, |v| match v.as_u64() {
+            Some(n) if n % 2 == 0 => None,
+            Some(n) => Some(json!(n * 10)),
+            None => Some(v),
+        });
+        assert_eq!(Ok(4), replaced);
+        assert_eq!(json!([10, 30, 5]), json["a"]);
+
+        let path = JsonPath::parse("$.b.*").unwrap();
+        assert_eq!(Ok(2), path.update(&mut json, |_| None));
+        assert_eq!(json!({}), json["b"]);
+
+        // descendants are replaced before their ancestors
+        let mut json = json!({"a": {"b": 1}});
+        let mut visited = vec![];
+        let path = JsonPath::parse("$..['a', 'b']").unwrap();
+        let replaced = path.update(&mut json, |v| {
+            visited.push(v.clone());
+            Some(v)
+        });
+        assert_eq!(Ok(2), replaced);
+        assert_eq!(vec![json!(1), json!({"b": 1})], visited);
+    }
+
+    #[test]
+    fn json_path_can_delete_values() {
+        let mut json = json!({"a": [0, 1, 2, 3], "b": {"c": 1, "d": 2}});
+        let path = JsonPath::parse("$.a[2,0]").unwrap();
+        assert_eq!(Ok(2), path.delete(&mut json));
+        assert_eq!(json!([1, 3]), json["a"]);
+
+        let mut json = json!({"a": [{"x": 1}, {"y": 1}, {"x": 2}, {"x": 3}]});
+        let path = JsonPath::parse("$.a[*][?(@.x)]").unwrap();
+        assert_eq!(Ok(3), path.delete(&mut json));
+        assert_eq!(json!({"a": [{"y": 1}]}), json);
+
+        // nested matches are removed along with their ancestors, duplicated matches only once
+        let mut json = json!({"a": {"a": {"b": 1}}, "b": [1, 2]});
+        let path = JsonPath::parse("$..['a', 'b']").unwrap();
+        assert_eq!(Ok(4), path.delete(&mut json));
+        assert_eq!(json!({}), json);
+        let path = JsonPath::parse("$.a[0, 0, -1]").unwrap();
+        let mut json = json!({"a": [1, 2, 3]});
+        assert_eq!(Ok(2), path.delete(&mut json));
+        assert_eq!(json!({"a": [2]}), json);
+
+        let path = JsonPath::parse("$").unwrap();
+        assert_eq!(Ok(0), path.delete(&mut json));
+    }
+
+    #[test]
+    fn json_path_can_insert_values() {
+        let mut json = json!({"config": {"name": "a"}});
+        let path = JsonPath::parse("$.config.db.replicas[0]").unwrap();
+        assert_eq!(Ok(None), path.insert(&mut json, Value::Null));
+        let path = JsonPath::parse("$.config.db.replicas[1].host").unwrap();
+        assert_eq!(Ok(None), path.insert(&mut json, json!("localhost")));
+        assert_eq!(
+            json!({"config": {"name": "a", "db": {"replicas": [null, {"host": "localhost"}]}}}),
+            json
+        );
+        assert_eq!(
+            Ok(Some(json!("localhost"))),
+            path.insert(&mut json, json!("remote"))
+        );
+        let path = JsonPath::parse("$.config.db.replicas[-2]").unwrap();
+        assert_eq!(Ok(Some(Value::Null)), path.insert(&mut json, json!({})));
+        assert_eq!(
+            json!([{}, {"host": "remote"}]),
+            json["config"]["db"]["replicas"]
+        );
+
+        let mut json = Value::Null;
+        let path = JsonPath::parse("$[0]['a']").unwrap();
+        assert_eq!(Ok(None), path.insert(&mut json, json!(1)));
+        assert_eq!(json!([{"a": 1}]), json);
+        let path = JsonPath::parse("$").unwrap();
+        assert_eq!(
+            Ok(Some(json!([{"a": 1}]))),
+            path.insert(&mut json, json!(2))
+        );
+    }
+
+    #[test]
+    fn json_path_rejects_invalid_insertions() {
+        let mut json = json!({"a": 1, "b": [1]});
+        for path in [
+            "$.a.b",
+            "$.a[0]",
+            "$.b.c",
+            "$.b[-2]",
+            "$.b[2]",
+            "$.b[-2147483648]",
+            "$.c[2147483647]",
+            "$.c.d[-1]",
+            "$.c[0][1]",
+            "$.b[*]",
+            "$..a",
+            "$['a', 'b']",
+        ] {
+            let path = JsonPath::parse(path).unwrap();
+            assert!(path.insert(&mut json, json!(2)).is_err(), "{}", path);
+        }
+        assert_eq!(json!({"a": 1, "b": [1]}), json);
+
+        // nothing is created when the insertion fails below missing nodes
+        let mut json = json!({"a": 1});
+        let path = JsonPath::parse("$.new.x[-1]").unwrap();
+        assert_eq!(
+            Err(JsonPathError::EvaluationError(
+                "Array index -1 is out of bounds, the array has 0 items.".to_string()
+            )),
+            path.insert(&mut json, json!(2))
+        );
+        assert_eq!(json!({"a": 1}), json);
+    }
+
+    #[test]
+    fn json_path_orders_strings_with_its_collation() {
+        let json = json!([{"name": "Max"}, {"name": "anna"}, {"name": "zoe"}]);
+        let path = JsonPath::parse("$[*][?@.name >= 'm'].name").unwrap();
+        assert_eq!(Ok(json!(["zoe"])), path.query(&json));
+        let path = path.with_collation(Collation::CaseInsensitive);
+        assert_eq!(Ok(json!(["Max", "zoe"])), path.query(&json));
+        assert_eq!(
+            Ok(vec![&json!("Max"), &json!("zoe")]),
+            path.query_ref(&json)
+        );
+        let nodes = path.iter(&json).collect::<JsonPathResult<Vec<_>>>();
+        assert_eq!(Ok(vec![&json!("Max"), &json!("zoe")]), nodes);
+        // equality agrees with the order
+        let path = JsonPath::parse("$[*][?(@.name >= 'max' && @.name <= 'max')].name").unwrap();
+        let path = path.with_collation(Collation::CaseInsensitive);
+        assert_eq!(Ok(json!(["Max"])), path.query(&json));
+        let path = JsonPath::parse("$[*][?(@.name == 'max')].name").unwrap();
+        assert_eq!(Ok(json!([])), path.query(&json));
+        let path = path.with_collation(Collation::CaseInsensitive);
+        assert_eq!(Ok(json!(["Max"])), path.query(&json));
+    }
+
+    #[test]
+    fn json_path_distinguishes_missing_from_null() {
+        let path = JsonPath::parse("$.a").unwrap();
+        assert_eq!(Ok(Some(Value::Null)), path.query_opt(&json!({"a": null})));
+        assert_eq!(Ok(None), path.query_opt(&json!({})));
+        assert_eq!(Ok(Value::Null), path.query(&json!({})));
+        let path = JsonPath::parse("$.a[*]").unwrap();
+        assert_eq!(Ok(Some(json!([]))), path.query_opt(&json!({"a": []})));
+
+        let path = JsonPath::parse("$['a', 'b']").unwrap();
+        let json = json!({"a": null});
+        assert_eq!(Ok(json!({"a": null, "b": null})), path.query(&json));
+        let path = path.with_omit_missing(true);
+        assert_eq!(Ok(json!({"a": null})), path.query(&json));
+        assert_eq!(Ok(json!({})), path.query(&json!({"c": 1})));
+    }
+
+    #[test]
+    fn json_path_knows_its_result_shape() {
+        let shape = |path: &str| {
+            let path = JsonPath::parse(path).unwrap();
+            (path.is_definite(), path.result_shape())
+        };
+        assert_eq!((true, ResultShape::Scalar), shape("$"));
+        assert_eq!((true, ResultShape::Scalar), shape("$.a[0]['b'][$index]"));
+        assert_eq!((false, ResultShape::Scalar), shape("$.a['b', 'c']"));
+        assert_eq!((false, ResultShape::Array), shape("$.a[*][?(@.b)]"));
+        assert_eq!((false, ResultShape::Array), shape("$.a['b', 'c'].d"));
+        assert_eq!((false, ResultShape::Array), shape("$.a[0, 1]"));
+        assert_eq!((false, ResultShape::Array), shape("$.a[1:]"));
+        assert_eq!((false, ResultShape::Array), shape("$..a"));
+
+        // a filter tests the current node itself in compat mode, and selects its members in
+        // standard mode
+        assert_eq!((false, ResultShape::Scalar), shape("$.a[?(@.b)]"));
+        let tokenizer = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let path = JsonPath::parse_with("$.a[?(@.b)]", &tokenizer).unwrap();
+        assert_eq!(ResultShape::Array, path.result_shape());
+
+        // the result shape does not depend on the json document
+        let path = JsonPath::parse("$.a[*]").unwrap();
+        assert_eq!(Ok(json!([])), path.query(&json!({})));
+        assert_eq!(Ok(json!([1])), path.query(&json!({"a": [1]})));
+    }
+
+    #[test]
+    fn json_path_can_pass_results_to_a_sink() {
+        struct FirstN<'a> {
+            n: usize,
+            nodes: Vec<(String, &'a Value)>,
+        }
+
+        impl<'a> ResultSink<'a> for FirstN<'a> {
+            fn accept(&mut self, path: &NormalizedPath<'a>, value: &'a Value) -> ControlFlow<()> {
+                self.nodes.push((path.to_string(), value));
+                if self.nodes.len() < self.n {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            }
+        }
+
+        let json = json!({"a": [{"b": 1}, {"b": 2}, {"b": 3}], "b": 4});
+        let path = JsonPath::parse("$..b").unwrap();
+        let mut sink = FirstN {
+            n: 2,
+            nodes: vec![],
+        };
+        path.query_into(&json, &mut sink).unwrap();
+        assert_eq!(
+            vec![
+                ("$['b']".to_string(), &json!(4)),
+                ("$['a'][0]['b']".to_string(), &json!(1))
+            ],
+            sink.nodes
+        );
+
+        let mut nodes: Vec<&Value> = vec![];
+        let path = JsonPath::parse("$.a[*][?(@.b > $min)].b").unwrap();
+        let bindings = Bindings::new().bind("min", 1);
+        path.query_into_with(&json, &bindings, &mut nodes).unwrap();
+        assert_eq!(vec![&json!(2), &json!(3)], nodes);
+    }
+
+    #[test]
+    fn json_path_can_iterate_results_lazily() {
+        let json = json!({"items": [{"id": 1}, {"id": 2}, {"id": 3}]});
+        let path = JsonPath::parse("$.items[*].id").unwrap();
+        let first = path.iter(&json).take(2).collect::<JsonPathResult<Vec<_>>>();
+        assert_eq!(Ok(vec![&json!(1), &json!(2)]), first);
+
+        let path = JsonPath::parse("$.items[$index].id").unwrap();
+        let bindings = Bindings::new().bind("index", -1);
+        assert_eq!(Some(Ok(&json!(3))), path.iter_with(&json, &bindings).next());
+        let mut iter = path.iter(&json);
+        assert!(matches!(iter.next(), Some(Err(_))));
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn json_path_displays_original_path() {
+        let path: JsonPath = "$['data'].msg".parse().unwrap();
+        assert_eq!("$['data'].msg", path.to_string());
+        assert_eq!("$['data'].msg", path.as_str());
+    }
+}
@@ -1,37 +1,77 @@
 mod constants;
+mod functions;
 mod tokens;
 use constants::*;
 use peekmore::PeekMore;
 use peekmore::PeekMoreIterator;
-use serde_json::Value;
+use serde_json::{Map, Value};
 
+pub use functions::*;
 use std::str::Chars;
 pub use tokens::*;
 mod stream;
 use crate::{JsonPathError, JsonPathResult};
 use stream::PeekableExt;
 
-pub struct Tokenizer {}
+pub struct Tokenizer {
+    functions: FunctionRegistry,
+    filter_mode: FilterMode,
+}
 pub type TokenStream<'a> = PeekMoreIterator<Chars<'a>>;
 
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Tokenizer {
     pub fn new() -> Tokenizer {
-        Tokenizer {}
+        Tokenizer {
+            functions: FunctionRegistry::new(),
+            filter_mode: FilterMode::Compat,
+        }
+    }
+
+    /// Use the registered functions in filter expressions, in addition to the built-in ones.
+    pub fn with_functions(mut self, functions: FunctionRegistry) -> Tokenizer {
+        self.functions = functions;
+        self
+    }
+
+    /// Apply filters as defined by the given mode, [`FilterMode::Compat`] by default.
+    pub fn with_filter_mode(mut self, filter_mode: FilterMode) -> Tokenizer {
+        self.filter_mode = filter_mode;
+        self
     }
 
+    /// Parse the jsonpath into tokens.
+    ///
+    /// The position of a returned [`JsonPathError::InvalidJsonPath`] is the char index of the
+    /// first char that is not consumed, it is set here for every error, so errors raised while
+    /// reading are created with position 0.
     pub fn tokenize(&self, jsonpath: &str) -> JsonPathResult<Vec<Token>> {
         let mut stream = jsonpath.chars().peekmore();
-        match self.read_json_path(&mut stream)? {
-            tokens if stream.next_significant().is_none() => Ok(tokens),
-            tokens => Err(JsonPathError::InvalidJsonPath(
+        let result = match self.read_json_path(&mut stream) {
+            Ok(tokens) if stream.next_significant().is_none() => Ok(tokens),
+            Ok(tokens) => Err(JsonPathError::InvalidJsonPath(
                 format!(
                     "Cannot parse the full jsonpath string, parsed tokens: {:?}, next char: {:?}",
                     tokens,
-                    stream.next_significant()
+                    stream.peek()
                 ),
-                stream.count(),
+                0,
             )),
-        }
+            Err(e) => Err(e),
+        };
+        // report the position of the first char not consumed by the tokenizer
+        result.map_err(|e| match e {
+            JsonPathError::InvalidJsonPath(msg, _) => {
+                let remaining = stream.clone().count();
+                JsonPathError::InvalidJsonPath(msg, jsonpath.chars().count() - remaining)
+            }
+            e => e,
+        })
     }
 
     fn read_json_path(&self, stream: &mut TokenStream<'_>) -> JsonPathResult<Vec<Token>> {
@@ -40,7 +80,7 @@ impl Tokenizer {
             x => {
                 return Err(JsonPathError::InvalidJsonPath(
                     format!("The jsonpath must start with '$' or '@', found: {:?}", x),
-                    stream.cursor(),
+                    0,
                 ))
             }
         };
@@ -63,14 +103,7 @@ impl Tokenizer {
         tokens: &mut Vec<Token>,
     ) -> JsonPathResult<bool> {
         match stream.peek().copied().unwrap() {
-            OPEN_SQUARE_BRACKET => {
-                let r = self.read_bracket_property_token(stream, tokens)?
-                    || self.read_array_token(stream, tokens)?
-                    || self.read_wildcard_token(stream, tokens)?
-                    || self.read_filter_token(stream, tokens)?
-                    || self.read_placeholder_token(stream, tokens)?;
-                Ok(r)
-            }
+            OPEN_SQUARE_BRACKET => self.read_bracket_token(stream, tokens),
             PERIOD => {
                 let r = self.read_dot_token(stream, tokens)?
                     || self.read_wildcard_token(stream, tokens)?
@@ -124,8 +157,10 @@ impl Tokenizer {
         let mut s: String = String::new();
         while let Some(c) = stream.peek() {
             match *c {
-                SPACE | PERIOD | OPEN_SQUARE_BRACKET | CLOSE_PARENTHESIS | CLOSE_SQUARE_BRACKET
-                | '&' | '|' | '>' | '<' | '=' | '!' | '~' => break,
+                // arithmetic operators end a name, except for `-`, which is valid within names
+                SPACE | PERIOD | COMMA | OPEN_SQUARE_BRACKET | CLOSE_PARENTHESIS
+                | CLOSE_SQUARE_BRACKET | '&' | '|' | '>' | '<' | '=' | '!' | '~' | '+' | '*'
+                | '/' | '%' => break,
                 OPEN_PARENTHESIS => {
                     is_function = true;
                     break;
@@ -137,7 +172,13 @@ impl Tokenizer {
             }
         }
         if is_function {
-            unimplemented!("function is not supported")
+            Err(JsonPathError::InvalidJsonPath(
+                format!(
+                    "Function {}() is only supported within filter expressions",
+                    s
+                ),
+                0,
+            ))
         } else if !s.is_empty() {
             Ok(Some(Token::property(s)))
         } else {
@@ -145,144 +186,144 @@ impl Tokenizer {
         }
     }
 
-    /// read ['a','b'] etc. properties within square brackets
-    fn read_bracket_property_token(
+    /// read the comma separated selectors within square brackets, e.g. ['a','b'], [0, -1],
+    /// [1:3], [*], [?@.id > 1], [$name] or any combination of them
+    fn read_bracket_token(
         &self,
         stream: &mut TokenStream<'_>,
         tokens: &mut Vec<Token>,
     ) -> JsonPathResult<bool> {
-        let potential_delimiter = match (
-            stream.peek_significant_and_move_on(),
-            stream.peek_significant().copied(),
-        ) {
-            (Some(OPEN_SQUARE_BRACKET), Some(c)) if c == SINGLE_QUOTE || c == DOUBLE_QUOTE => {
-                stream.truncate_iterator_to_cursor();
-                c
-            }
-            _ => {
-                stream.reset_cursor();
-                return Ok(false);
+        match stream.next_significant() {
+            Some(OPEN_SQUARE_BRACKET) => {}
+            x => {
+                return Err(JsonPathError::InvalidJsonPath(
+                    format!("Expect [, found: {:?}", x),
+                    0,
+                ))
             }
-        };
+        }
 
-        let mut props: Vec<String> = vec![];
-        let mut in_property = false;
-        let mut in_escape = false;
-        let mut current_prop = String::new();
-        while let Some(c) = stream.next() {
-            match c {
-                _ if in_escape => in_escape = false,
-                ESCAPE => in_escape = true,
-                CLOSE_SQUARE_BRACKET if !in_property => {
-                    break;
-                }
-                c if c == potential_delimiter && in_property => {
-                    props.push(current_prop.clone());
-                    in_property = false;
-                    stream.drop_while(|c| c.is_whitespace());
-                    match stream.peek() {
-                        Some(c) if *c != CLOSE_SQUARE_BRACKET && *c != COMMA => {
-                            return Err(JsonPathError::InvalidJsonPath(
-                                format!("Read square bracket property failed, expect , or ], found: {:?}", c),
-                                stream.cursor(),
-                            ));
-                        }
-                        _ => {}
-                    }
-                }
-                c if c == potential_delimiter && !in_property => {
-                    current_prop = String::new();
-                    in_property = true;
-                }
-                COMMA if !in_property => {
-                    stream.drop_while(|c| c.is_whitespace());
-                    match stream.peek() {
-                        // TODO: consider support diff delimiter?
-                        Some(c) if *c == potential_delimiter => {}
-                        _ => {
-                            return Err(JsonPathError::InvalidJsonPath(
-                                "Expecte delimiter after comma".to_string(),
-                                stream.cursor(),
-                            ))
-                        }
-                    }
+        let mut selectors = vec![];
+        loop {
+            selectors.push(self.read_selector(stream)?);
+            stream.reset_cursor();
+            match stream.next_significant() {
+                Some(COMMA) => {}
+                Some(CLOSE_SQUARE_BRACKET) => break,
+                x => {
+                    return Err(JsonPathError::InvalidJsonPath(
+                        format!("Expect , or ] after a selector, found: {:?}", x),
+                        0,
+                    ))
                 }
-                _ => current_prop.push(c),
             }
         }
 
-        if in_property {
-            return Err(JsonPathError::InvalidJsonPath(
-                "Incomplete property".to_string(),
-                stream.cursor(),
-            ));
-        }
-
-        tokens.push(Token::properties(props));
+        tokens.push(Token::selection(selectors));
         match stream.peek() {
             None => Ok(true),
             Some(_) => self.read_next_token(stream, tokens),
         }
     }
 
-    fn read_array_token(
-        &self,
-        stream: &mut TokenStream<'_>,
-        tokens: &mut Vec<Token>,
-    ) -> JsonPathResult<bool> {
-        match (
-            stream.peek_significant_and_move_on(),
-            stream.peek_significant().copied(),
-        ) {
-            (Some(OPEN_SQUARE_BRACKET), Some(c))
-                if c.is_ascii_digit() || c == MINUS || c == SPLIT =>
-            {
-                stream.truncate_iterator_to_cursor();
+    /// read a single selector within square brackets
+    fn read_selector(&self, stream: &mut TokenStream<'_>) -> JsonPathResult<Selector> {
+        stream.reset_cursor();
+        stream.drop_while(|c| c.is_whitespace());
+        match stream.peek().copied() {
+            Some(SINGLE_QUOTE) | Some(DOUBLE_QUOTE) => {
+                stream.read_quoted_string().map(Selector::Name)
             }
-            _ => {
-                stream.reset_cursor();
-                return Ok(false);
+            Some(WILDCARD) => {
+                stream.next();
+                Ok(Selector::Wildcard)
+            }
+            Some(BEGIN_FILTER) => {
+                // both [?(@.id > 1)] and [?@.id > 1]
+                stream.next();
+                let expression = self.expr(0, stream)?;
+                self.check_test_expr(&expression)?;
+                match self.filter_mode {
+                    FilterMode::Standard => Ok(Selector::Filter(expression)),
+                    FilterMode::Compat => Ok(Selector::Predicate(expression)),
+                }
+            }
+            Some(DOC_CONTEXT) => self
+                .read_placeholder(stream, PlaceholderType::Selector)
+                .map(Selector::Placeholder),
+            Some(c) if c.is_ascii_digit() || c == MINUS || c == SPLIT => {
+                let mut expr = String::new();
+                while let Some(c) = stream.peek() {
+                    if c.is_ascii_digit() || *c == MINUS || *c == SPLIT || c.is_whitespace() {
+                        expr.push(*c);
+                        stream.next();
+                    } else {
+                        break;
+                    }
+                }
+                if expr.contains(SPLIT) {
+                    ArraySlice::parse(&expr).map(Selector::Slice)
+                } else {
+                    Token::as_i32(&expr).map(Selector::Index)
+                }
             }
+            x => Err(JsonPathError::InvalidJsonPath(
+                format!("Expect a selector, found: {:?}", x),
+                0,
+            )),
         }
+    }
 
-        // try get array index, after the loop, next token should be ]
-        let mut expr = String::new();
+    /// read a placeholder name, e.g. $id
+    fn read_placeholder(
+        &self,
+        stream: &mut TokenStream<'_>,
+        expected: PlaceholderType,
+    ) -> JsonPathResult<PlaceholderToken> {
+        match stream.next_significant() {
+            Some(DOC_CONTEXT) => {}
+            x => {
+                return Err(JsonPathError::InvalidJsonPath(
+                    format!("Expect placeholder, found: {:?}", x),
+                    0,
+                ))
+            }
+        }
+        let mut name = String::new();
         while let Some(c) = stream.peek() {
-            if c.is_ascii_digit() || *c == MINUS || *c == SPLIT || c.is_whitespace() || *c == COMMA
-            {
-                expr.push(*c);
+            if c.is_ascii_alphanumeric() || *c == '_' {
+                name.push(*c);
                 stream.next();
             } else {
                 break;
             }
         }
-
-        // check expr is present, next token is ]
-        match stream.next() {
-            Some(CLOSE_SQUARE_BRACKET) => {
-                if expr.contains(SPLIT) {
-                    tokens.push(Token::array_slice(expr)?);
-                } else {
-                    tokens.push(Token::array_index(expr)?);
-                }
-                match stream.peek() {
-                    None => Ok(true),
-                    Some(_) => self.read_next_token(stream, tokens),
-                }
+        match name.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                Ok(PlaceholderToken { name, expected })
             }
             _ => Err(JsonPathError::InvalidJsonPath(
-                "Expect ] to close array index/slice query.".to_string(),
+                format!("Invalid placeholder name: {:?}", name),
                 0,
             )),
         }
     }
 
-    fn read_placeholder_token(
-        &self,
-        _stream: &mut TokenStream<'_>,
-        _tokens: &mut Vec<Token>,
-    ) -> JsonPathResult<bool> {
-        Ok(false)
+    /// whether the stream is at a placeholder, i.e. '$' followed by a name
+    fn is_placeholder(&self, stream: &mut TokenStream<'_>) -> bool {
+        let is_placeholder = matches!(
+            (stream.peek().copied(), stream.peek_next().copied()),
+            (Some(DOC_CONTEXT), Some(c)) if c.is_ascii_alphabetic() || c == '_'
+        );
+        stream.reset_cursor();
+        is_placeholder
+    }
+
+    /// infer the type of values that can be bound to a placeholder from where it is used
+    fn expect_placeholder(expression: &mut Expression, expected: PlaceholderType) {
+        if let Expression::Placeholder(placeholder) = expression {
+            placeholder.expected = expected;
+        }
     }
 
     fn read_wildcard_token(
@@ -294,21 +335,6 @@ impl Tokenizer {
             stream.peek_significant_and_move_on(),
             stream.peek_significant_and_move_on(),
         ) {
-            (Some(OPEN_SQUARE_BRACKET), Some(WILDCARD)) => {
-                stream.truncate_iterator_to_cursor();
-                if let Some(CLOSE_SQUARE_BRACKET) = stream.next_significant() {
-                    tokens.push(Token::Wildcard);
-                    match stream.peek() {
-                        None => Ok(true),
-                        Some(_) => self.read_next_token(stream, tokens),
-                    }
-                } else {
-                    Err(JsonPathError::InvalidJsonPath(
-                        "Expect ] to close wildcard query.".to_string(),
-                        0,
-                    ))
-                }
-            }
             (Some(PERIOD), Some(WILDCARD)) => {
                 stream.truncate_iterator_to_cursor();
                 tokens.push(Token::Wildcard);
@@ -341,14 +367,16 @@ impl Tokenizer {
                     // TODO: add position info
                     return Err(JsonPathError::InvalidJsonPath(
                         "Unexpected '.' in the jsonpath.".to_string(),
-                        stream.cursor(),
+                        0,
                     ));
                 }
-                let opt_token = self.read_property_or_function_name(stream)?;
-                match opt_token {
-                    Some(token) => tokens.push(token),
-                    None => {}
-                };
+                if let Some(WILDCARD) = stream.peek().copied() {
+                    // `..*` selects all descendants, `*` ends other names
+                    stream.next();
+                    tokens.push(Token::Wildcard);
+                } else if let Some(token) = self.read_property_or_function_name(stream)? {
+                    tokens.push(token);
+                }
                 match stream.peek() {
                     Some(_) => self.read_next_token(stream, tokens),
                     None => Ok(true),
@@ -363,42 +391,6 @@ impl Tokenizer {
 }
 
 impl Tokenizer {
-    fn read_filter_token(
-        &self,
-        stream: &mut TokenStream<'_>,
-        tokens: &mut Vec<Token>,
-    ) -> JsonPathResult<bool> {
-        match (
-            stream.peek_significant_and_move_on(),
-            stream.peek_significant_and_move_on(),
-            stream.peek_significant_and_move_on(),
-        ) {
-            (Some(OPEN_SQUARE_BRACKET), Some(BEGIN_FILTER), Some(OPEN_PARENTHESIS)) => {
-                stream.truncate_iterator_to_cursor();
-                // it starts with "[?(", so assuming it's a filter: [?(Expression)]
-                let expression = self.expr(0, stream)?;
-                tokens.push(Token::Predicate(expression));
-                match (stream.next_significant(), stream.next_significant()) {
-                    (Some(CLOSE_PARENTHESIS), Some(CLOSE_SQUARE_BRACKET)) => match stream.peek() {
-                        None => Ok(true),
-                        _ => self.read_next_token(stream, tokens),
-                    },
-                    (x, y) => Err(JsonPathError::InvalidJsonPath(
-                        format!(
-                            "Expect close of filter, found: {:?}, {:?}, tokens: {:?}",
-                            x, y, tokens
-                        ),
-                        stream.cursor(),
-                    )),
-                }
-            }
-            _ => {
-                stream.reset_cursor();
-                Ok(false)
-            }
-        }
-    }
-
     fn expr(&self, bp: i32, tokens: &mut TokenStream<'_>) -> JsonPathResult<Expression> {
         let mut expression = self.nud(bp, tokens)?;
         while let Some(t) = tokens.peek_significant() {
@@ -439,12 +431,22 @@ impl Tokenizer {
             Ok(Some(Comparator::LtEq))
         } else if stream.peek_matches_ignore_case("<")? {
             Ok(Some(Comparator::Lt))
-        } else if stream.peek_matches_ignore_case("~=")? {
+        } else if stream.peek_matches_ignore_case("=~")? {
             Ok(Some(Comparator::RegExpMatch))
         } else if stream.peek_matches_ignore_case("&&")? {
             Ok(Some(Comparator::AND))
         } else if stream.peek_matches_ignore_case("||")? {
             Ok(Some(Comparator::OR))
+        } else if stream.peek_matches_ignore_case("+")? {
+            Ok(Some(Comparator::Add))
+        } else if stream.peek_matches_ignore_case("-")? {
+            Ok(Some(Comparator::Sub))
+        } else if stream.peek_matches_ignore_case("*")? {
+            Ok(Some(Comparator::Mul))
+        } else if stream.peek_matches_ignore_case("/")? {
+            Ok(Some(Comparator::Div))
+        } else if stream.peek_matches_ignore_case("%")? {
+            Ok(Some(Comparator::Rem))
         } else if stream.peek_matches_ignore_case("in")? {
             Ok(Some(Comparator::IN))
         } else if stream.peek_matches_ignore_case("nin")? {
@@ -457,6 +459,14 @@ impl Tokenizer {
             Ok(Some(Comparator::NoneOf))
         } else if stream.peek_matches_ignore_case("contains")? {
             Ok(Some(Comparator::Contains))
+        } else if stream.peek_matches_ignore_case("icontains")? {
+            Ok(Some(Comparator::IContains))
+        } else if stream.peek_matches_ignore_case("startswith")? {
+            Ok(Some(Comparator::StartsWith))
+        } else if stream.peek_matches_ignore_case("endswith")? {
+            Ok(Some(Comparator::EndsWith))
+        } else if stream.peek_matches_ignore_case("ieq")? {
+            Ok(Some(Comparator::IEq))
         } else if stream.peek_matches_ignore_case("size")? {
             Ok(Some(Comparator::SizeOf))
         } else if stream.peek_matches_ignore_case("empty")? {
@@ -467,25 +477,56 @@ impl Tokenizer {
     }
 
     fn expr_eof(&self, c: &char) -> bool {
-        *c == CLOSE_PARENTHESIS
+        *c == CLOSE_PARENTHESIS || *c == CLOSE_SQUARE_BRACKET || *c == COMMA
     }
 
     fn bp(&self, c: &Comparator) -> i32 {
         match c {
             Comparator::AND => 3,
             Comparator::OR => 2,
+            Comparator::Add | Comparator::Sub => 20,
+            Comparator::Mul | Comparator::Div | Comparator::Rem => 30,
             _ => 10,
         }
     }
 
     fn led(
         &self,
-        left: Expression,
+        mut left: Expression,
         comparator: Comparator,
         streams: &mut TokenStream<'_>,
     ) -> JsonPathResult<Expression> {
         let bp = self.bp(&comparator);
-        let right = self.expr(bp, streams)?;
+        let mut right = match comparator {
+            Comparator::RegExpMatch => self.read_regex(streams)?,
+            _ => self.expr(bp, streams)?,
+        };
+        match comparator {
+            Comparator::IN | Comparator::NIN => {
+                Self::expect_placeholder(&mut right, PlaceholderType::Array);
+            }
+            Comparator::SubsetOf | Comparator::AnyOf | Comparator::NoneOf => {
+                Self::expect_placeholder(&mut left, PlaceholderType::Array);
+                Self::expect_placeholder(&mut right, PlaceholderType::Array);
+            }
+            Comparator::SizeOf => Self::expect_placeholder(&mut right, PlaceholderType::Number),
+            Comparator::Empty => Self::expect_placeholder(&mut right, PlaceholderType::Bool),
+            Comparator::Sub | Comparator::Mul | Comparator::Div | Comparator::Rem => {
+                Self::expect_placeholder(&mut left, PlaceholderType::Number);
+                Self::expect_placeholder(&mut right, PlaceholderType::Number);
+            }
+            _ => {}
+        }
+        match comparator {
+            Comparator::AND | Comparator::OR => {
+                self.check_test_expr(&left)?;
+                self.check_test_expr(&right)?;
+            }
+            _ => {
+                self.check_comparable(&left)?;
+                self.check_comparable(&right)?;
+            }
+        }
         Ok(Expression::CompareExpr {
             op: comparator,
             left: Box::new(left),
@@ -493,6 +534,22 @@ impl Tokenizer {
         })
     }
 
+    /// read the regular expression literal on the right side of `=~`
+    fn read_regex(&self, stream: &mut TokenStream<'_>) -> JsonPathResult<Expression> {
+        match stream.peek_significant().copied() {
+            Some(SLASH) => {
+                stream.truncate_iterator_to_cursor();
+                let (pattern, flags) = stream.read_regex()?;
+                RegexLiteral::new(pattern, flags).map(Expression::Regex)
+            }
+            x => Err(JsonPathError::InvalidJsonPath(
+                format!("Expect regular expression after =~, found: {:?}", x),
+                0,
+            )),
+        }
+    }
+
+    /// read a JSON literal, e.g. 'a', 1e6, null, [1, [2]] or {"x": 1}
     fn read_literal(&self, stream: &mut TokenStream<'_>) -> JsonPathResult<Value> {
         let c = stream.peek_significant().copied();
         stream.truncate_iterator_to_cursor();
@@ -505,27 +562,66 @@ impl Tokenizer {
                 let n = stream.read_number()?;
                 Ok(n)
             }
-            Some('t') | Some('T') => {
-                if stream.peek_matches_ignore_case("true")? {
+            Some('t') | Some('T') => self.read_keyword(stream, "true", Value::Bool(true)),
+            Some('f') | Some('F') => self.read_keyword(stream, "false", Value::Bool(false)),
+            Some('n') | Some('N') => self.read_keyword(stream, "null", Value::Null),
+            Some(OPEN_SQUARE_BRACKET) => {
+                stream.next();
+                let mut values = Vec::new();
+                if stream.peek_significant() == Some(&CLOSE_SQUARE_BRACKET) {
                     stream.truncate_iterator_to_cursor();
-                    Ok(Value::Bool(true))
-                } else {
-                    Err(JsonPathError::InvalidJsonPath(
-                        "Expect boolean true literal".to_string(),
-                        0,
-                    ))
+                    stream.next();
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.read_literal(stream)?);
+                    match stream.next_significant() {
+                        Some(COMMA) => {}
+                        Some(CLOSE_SQUARE_BRACKET) => break,
+                        x => {
+                            return Err(JsonPathError::InvalidJsonPath(
+                                format!("Expect , or ] in array literal, found: {:?}", x),
+                                0,
+                            ))
+                        }
+                    }
                 }
+                Ok(Value::Array(values))
             }
-            Some('f') | Some('F') => {
-                if stream.peek_matches_ignore_case("false")? {
+            Some(OPEN_BRACE) => {
+                stream.next();
+                let mut object = Map::new();
+                if stream.peek_significant() == Some(&CLOSE_BRACE) {
                     stream.truncate_iterator_to_cursor();
-                    Ok(Value::Bool(false))
-                } else {
-                    Err(JsonPathError::InvalidJsonPath(
-                        "Expect boolean false literal".to_string(),
-                        0,
-                    ))
+                    stream.next();
+                    return Ok(Value::Object(object));
+                }
+                loop {
+                    stream.reset_cursor();
+                    let key = stream.read_quoted_string()?;
+                    match stream.next_significant() {
+                        Some(SPLIT) => {}
+                        x => {
+                            return Err(JsonPathError::InvalidJsonPath(
+                                format!("Expect : in object literal, found: {:?}", x),
+                                0,
+                            ))
+                        }
+                    }
+                    let value = self.read_literal(stream)?;
+                    object.insert(key, value);
+                    match stream.next_significant() {
+                        Some(COMMA) => {}
+                        Some(CLOSE_BRACE) => break,
+                        x => {
+                            return Err(JsonPathError::InvalidJsonPath(
+                                format!("Expect , or }} in object literal, found: {:?}", x),
+                                0,
+                            ))
+                        }
+                    }
                 }
+                Ok(Value::Object(object))
             }
             _ => Err(JsonPathError::InvalidJsonPath(
                 format!("Expect literal, found {:?}", c),
@@ -534,11 +630,38 @@ impl Tokenizer {
         }
     }
 
+    /// read a keyword literal like true, the keyword is matched case-insensitively
+    fn read_keyword(
+        &self,
+        stream: &mut TokenStream<'_>,
+        keyword: &str,
+        value: Value,
+    ) -> JsonPathResult<Value> {
+        if stream.peek_matches_ignore_case(keyword)? {
+            stream.truncate_iterator_to_cursor();
+            Ok(value)
+        } else {
+            Err(JsonPathError::InvalidJsonPath(
+                format!("Expect {} literal", keyword),
+                0,
+            ))
+        }
+    }
+
     fn nud(&self, _bp: i32, stream: &mut TokenStream<'_>) -> JsonPathResult<Expression> {
         let c = stream.peek_significant().copied();
         stream.truncate_iterator_to_cursor();
         match c {
-            Some(DOC_CONTEXT) | Some(EVAL_CONTEXT) => {
+            Some(DOC_CONTEXT) if self.is_placeholder(stream) => self
+                .read_placeholder(stream, PlaceholderType::Any)
+                .map(Expression::Placeholder),
+            Some(DOC_CONTEXT) => {
+                // the number of chars left identifies the position of the query
+                let id = stream.clone().count();
+                let tokens = self.read_json_path(stream)?;
+                Ok(Expression::RootQuery { id, tokens })
+            }
+            Some(EVAL_CONTEXT) => {
                 let tokens = self.read_json_path(stream)?;
                 Ok(Expression::JsonQuery(tokens))
             }
@@ -556,55 +679,201 @@ impl Tokenizer {
             Some(NOT) => {
                 stream.next();
                 let expression = self.expr(1000, stream)?;
+                self.check_test_expr(&expression)?;
                 Ok(Expression::Not(Box::new(expression)))
             }
-            Some(OPEN_SQUARE_BRACKET) => {
-                // array or set literal
-                let mut values = Vec::new();
-                loop {
-                    match stream.next_significant() {
-                        Some(CLOSE_SQUARE_BRACKET) => {
-                            break;
-                        }
-                        Some(COMMA) | Some(OPEN_SQUARE_BRACKET) => {
-                            let expression = self.read_literal(stream)?;
-                            values.push(expression);
-                        }
-                        x => {
-                            return Err(JsonPathError::InvalidJsonPath(
-                                format!("expect array literals, found: {:?}", x),
-                                0,
-                            ))
-                        }
-                    }
-                }
-                Ok(Expression::Literal(Value::Array(values)))
+            Some(OPEN_SQUARE_BRACKET) | Some(OPEN_BRACE) => {
+                self.read_literal(stream).map(Expression::Literal)
             }
             Some(SINGLE_QUOTE) | Some(DOUBLE_QUOTE) => {
                 self.read_literal(stream).map(Expression::Literal)
             }
+            Some(MINUS) if !stream.peek_nth(1).is_some_and(char::is_ascii_digit) => {
+                // unary minus, binds tighter than any operator
+                stream.next();
+                let mut expression = self.expr(100, stream)?;
+                Self::expect_placeholder(&mut expression, PlaceholderType::Number);
+                self.check_comparable(&expression)?;
+                Ok(Expression::Negate(Box::new(expression)))
+            }
             Some(c) if c.is_ascii_digit() || c == MINUS => {
                 self.read_literal(stream).map(Expression::Literal)
             }
+            Some(c) if c.is_ascii_lowercase() && self.peek_function_name(stream).is_some() => {
+                self.read_function(stream).map(Expression::Function)
+            }
             Some('t') | Some('T') => self.read_literal(stream).map(Expression::Literal),
             Some('f') | Some('F') => self.read_literal(stream).map(Expression::Literal),
+            Some('n') | Some('N') => self.read_literal(stream).map(Expression::Literal),
             _ => Err(JsonPathError::InvalidJsonPath(
                 "Expect expressions.".to_string(),
-                stream.cursor(),
+                0,
             )),
         }
     }
 }
 
+// function extensions, see RFC 9535 section 2.4
+impl Tokenizer {
+    /// peek a function name followed by '(', the cursor is reset afterwards
+    fn peek_function_name(&self, stream: &mut TokenStream<'_>) -> Option<String> {
+        let mut name = String::new();
+        while let Some(c) = stream.peek() {
+            if c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '_' {
+                name.push(*c);
+                stream.advance_cursor();
+            } else {
+                break;
+            }
+        }
+        let is_function = !name.is_empty() && stream.peek() == Some(&OPEN_PARENTHESIS);
+        stream.reset_cursor();
+        Some(name).filter(|_| is_function)
+    }
+
+    fn read_function(&self, stream: &mut TokenStream<'_>) -> JsonPathResult<FunctionPathToken> {
+        let name = self.peek_function_name(stream).unwrap_or_default();
+        let function = Function::from_name(&name)
+            .or_else(|| self.functions.get(&name).map(Function::Custom))
+            .ok_or_else(|| {
+                JsonPathError::InvalidJsonPath(format!("Unknown function: {}", name), 0)
+            })?;
+        // skip the function name and the open parenthesis
+        stream.nth(name.chars().count());
+
+        let mut args = vec![];
+        if let Some(CLOSE_PARENTHESIS) = stream.peek_significant().copied() {
+            stream.truncate_iterator_to_cursor();
+            stream.next();
+        } else {
+            loop {
+                stream.reset_cursor();
+                args.push(self.expr(0, stream)?);
+                stream.reset_cursor();
+                match stream.next_significant() {
+                    Some(COMMA) => {}
+                    Some(CLOSE_PARENTHESIS) => break,
+                    x => {
+                        return Err(JsonPathError::InvalidJsonPath(
+                            format!(
+                                "Expect , or ) in arguments of function {}(), found: {:?}",
+                                name, x
+                            ),
+                            0,
+                        ))
+                    }
+                }
+            }
+        }
+
+        let parameters = function.parameters();
+        if parameters.len() != args.len() {
+            return Err(JsonPathError::InvalidJsonPath(
+                format!(
+                    "Function {}() expects {} argument(s), found {}",
+                    name,
+                    parameters.len(),
+                    args.len()
+                ),
+                0,
+            ));
+        }
+        for (index, (parameter, arg)) in parameters.iter().zip(args.iter()).enumerate() {
+            if !Self::is_well_typed(arg, *parameter) {
+                return Err(JsonPathError::InvalidJsonPath(
+                    format!(
+                        "Argument {} of function {}() is not of {:?}",
+                        index + 1,
+                        name,
+                        parameter
+                    ),
+                    0,
+                ));
+            }
+        }
+
+        // regular expressions given as literals are compiled only once
+        if let (Function::Match | Function::Search, Some(Expression::Literal(Value::String(p)))) =
+            (&function, args.get(1))
+        {
+            let regex = match function {
+                Function::Match => RegexLiteral::full_match(p.clone())?,
+                _ => RegexLiteral::new(p.clone(), String::new())?,
+            };
+            args[1] = Expression::Regex(regex);
+        }
+        Ok(FunctionPathToken { function, args })
+    }
+
+    /// check if the expression can be used where the given type is expected
+    fn is_well_typed(expression: &Expression, expected: FunctionType) -> bool {
+        match (expected, expression) {
+            (_, Expression::Function(f)) => match (expected, f.function.result_type()) {
+                (FunctionType::LogicalType, FunctionType::NodesType) => true,
+                (expected, actual) => expected == actual,
+            },
+            (FunctionType::ValueType, Expression::Literal(_))
+            | (FunctionType::ValueType, Expression::Placeholder(_))
+            | (FunctionType::ValueType, Expression::Negate(_)) => true,
+            (FunctionType::ValueType, Expression::CompareExpr { op, .. }) => op.is_arithmetic(),
+            (FunctionType::LogicalType, Expression::CompareExpr { op, .. }) => !op.is_arithmetic(),
+            (FunctionType::ValueType, Expression::JsonQuery(tokens))
+            | (FunctionType::ValueType, Expression::RootQuery { tokens, .. }) => {
+                tokens.iter().all(Token::is_singular)
+            }
+            (FunctionType::LogicalType, Expression::JsonQuery(_))
+            | (FunctionType::LogicalType, Expression::RootQuery { .. })
+            | (FunctionType::LogicalType, Expression::Not(_))
+            | (FunctionType::NodesType, Expression::JsonQuery(_))
+            | (FunctionType::NodesType, Expression::RootQuery { .. }) => true,
+            _ => false,
+        }
+    }
+
+    /// functions used in comparisons must return a ValueType
+    fn check_comparable(&self, expression: &Expression) -> JsonPathResult<()> {
+        match expression {
+            Expression::Function(f) if f.function.result_type() != FunctionType::ValueType => {
+                Err(JsonPathError::InvalidJsonPath(
+                    format!(
+                        "Result of function {}() is not comparable",
+                        f.function.name()
+                    ),
+                    0,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// functions used as test expressions must return a LogicalType or a NodesType
+    fn check_test_expr(&self, expression: &Expression) -> JsonPathResult<()> {
+        match expression {
+            Expression::Function(f) if f.function.result_type() == FunctionType::ValueType => {
+                Err(JsonPathError::InvalidJsonPath(
+                    format!(
+                        "Result of function {}() can not be used as a test expression",
+                        f.function.name()
+                    ),
+                    0,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use serde_json::json;
+
     use crate::tokenizer::Token;
 
     use super::*;
 
     #[test]
     fn tokenizer_supports_query_root() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize(" $ ")?;
         let expected = vec![Token::root('$')];
         assert_eq!(expected, tokens);
@@ -613,7 +882,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_query_properties() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data.id")?;
 
         let expected = vec![
@@ -627,7 +896,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_scan_properties() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data..id")?;
 
         let expected = vec![
@@ -637,12 +906,27 @@ mod test {
             Token::property("id".to_string()),
         ];
         assert_eq!(expected, tokens);
+
+        let tokens = tz.tokenize("$..*")?;
+        assert_eq!(
+            vec![Token::root('$'), Token::scan(), Token::Wildcard],
+            tokens
+        );
+        let tokens = tz.tokenize("$.a..*.b")?;
+        let expected = vec![
+            Token::root('$'),
+            Token::property("a".to_string()),
+            Token::scan(),
+            Token::Wildcard,
+            Token::property("b".to_string()),
+        ];
+        assert_eq!(expected, tokens);
         Ok(())
     }
 
     #[test]
     fn tokenizer_supports_square_bracket_properties() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$['data', 'value']..id")?;
 
         let expected = vec![
@@ -657,7 +941,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_square_bracket_properties_with_white_spaces() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$[ 'data' , ' val ue '  ]..id")?;
 
         let expected = vec![
@@ -673,7 +957,7 @@ mod test {
     #[test]
     fn tokenizer_should_fail_if_no_delimiter_after_comman_when_parsing_bracket_properties(
     ) -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let result = tz.tokenize("$[ 'data' , uexpected' val ue '  ]..id");
         assert!(result.is_err());
         Ok(())
@@ -681,7 +965,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_array_index() {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$[ 101 ]..id");
 
         let expected = vec![
@@ -695,7 +979,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_array_slice() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$[101 : 200 ]..id")?;
 
         let expected = vec![
@@ -710,7 +994,7 @@ mod test {
 
     #[test]
     fn tokenizer_reports_error_for_invalid_array_slice() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$[ 101 : 2 00 ]..id");
         assert!(tokens.is_err());
         Ok(())
@@ -718,7 +1002,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(@['id']!=' xxx' )]");
         assert!(tokens.is_ok());
         Ok(())
@@ -726,7 +1010,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter2() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(@['id']>=2)]");
         assert!(tokens.is_ok());
         Ok(())
@@ -734,7 +1018,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter3() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(@['id'] >= 2 || @.msg empty false)]");
 
         assert!(tokens.is_ok());
@@ -743,7 +1027,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter4() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens =
             tz.tokenize("$.data[?(@['id'] >= 2 || @.msg[?(@.value contains 'xx')] empty false)]");
         assert!(tokens.is_ok());
@@ -752,7 +1036,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter5() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(@ empty false)]");
         assert!(tokens.is_ok());
         Ok(())
@@ -760,7 +1044,7 @@ mod test {
 
     #[test]
     fn tokenizer_supports_basic_filter_in() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(@.id in ['a', 'b', 1])]");
         assert!(tokens.is_ok());
         Ok(())
@@ -768,15 +1052,248 @@ mod test {
 
     #[test]
     fn tokenizer_supports_not_filter() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(!@.is_true||@.is_false)]");
         assert!(tokens.is_ok());
         Ok(())
     }
 
+    #[test]
+    fn tokenizer_identifies_root_queries_by_position() -> JsonPathResult<()> {
+        let tokens = Tokenizer::new()
+            .with_filter_mode(FilterMode::Standard)
+            .tokenize("$.a[?(@.b == $.c && @.b == $.c)]")?;
+        let expected = Token::Filter(Expression::CompareExpr {
+            op: Comparator::AND,
+            left: Box::new(Expression::CompareExpr {
+                op: Comparator::Eq,
+                left: Box::new(Expression::JsonQuery(vec![
+                    Token::root('@'),
+                    Token::property("b".to_string()),
+                ])),
+                right: Box::new(Expression::RootQuery {
+                    id: 19,
+                    tokens: vec![Token::root('$'), Token::property("c".to_string())],
+                }),
+            }),
+            right: Box::new(Expression::CompareExpr {
+                op: Comparator::Eq,
+                left: Box::new(Expression::JsonQuery(vec![
+                    Token::root('@'),
+                    Token::property("b".to_string()),
+                ])),
+                right: Box::new(Expression::RootQuery {
+                    id: 5,
+                    tokens: vec![Token::root('$'), Token::property("c".to_string())],
+                }),
+            }),
+        });
+        assert_eq!(Some(&expected), tokens.last());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_regex_filter() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let tokens = tz.tokenize("$.data[?(@.name =~ /foo\\/.*?/i && @.id > 1)]")?;
+        let expected = Token::Filter(Expression::CompareExpr {
+            op: Comparator::AND,
+            left: Box::new(Expression::CompareExpr {
+                op: Comparator::RegExpMatch,
+                left: Box::new(Expression::JsonQuery(vec![
+                    Token::root('@'),
+                    Token::property("name".to_string()),
+                ])),
+                right: Box::new(Expression::Regex(RegexLiteral::new(
+                    "foo/.*?".to_string(),
+                    "i".to_string(),
+                )?)),
+            }),
+            right: Box::new(Expression::CompareExpr {
+                op: Comparator::Gt,
+                left: Box::new(Expression::JsonQuery(vec![
+                    Token::root('@'),
+                    Token::property("id".to_string()),
+                ])),
+                right: Box::new(Expression::Literal(serde_json::json!(1))),
+            }),
+        });
+        assert_eq!(Some(&expected), tokens.last());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_reports_error_for_invalid_regex() {
+        let tz = Tokenizer::new();
+        assert!(tz.tokenize("$.data[?(@.name =~ 'foo')]").is_err());
+        assert!(tz.tokenize("$.data[?(@.name =~ /foo)]").is_err());
+        assert!(tz.tokenize("$.data[?(@.name =~ /(foo/)]").is_err());
+        assert!(tz.tokenize("$.data[?(@.name =~ /foo/x)]").is_err());
+        assert!(tz.tokenize("$.data[?(/foo/)]").is_err());
+    }
+
+    #[test]
+    fn tokenizer_supports_functions() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let tokens = tz.tokenize("$.data[?(length(@.tags) > 2)]")?;
+        let expected = Token::Filter(Expression::CompareExpr {
+            op: Comparator::Gt,
+            left: Box::new(Expression::Function(FunctionPathToken {
+                function: Function::Length,
+                args: vec![Expression::JsonQuery(vec![
+                    Token::root('@'),
+                    Token::property("tags".to_string()),
+                ])],
+            })),
+            right: Box::new(Expression::Literal(serde_json::json!(2))),
+        });
+        assert_eq!(Some(&expected), tokens.last());
+
+        let tokens = tz.tokenize("$.data[?(match(@.code, 'A[0-9]+') && count(@.*) == 1)]")?;
+        assert_eq!(3, tokens.len());
+        let tokens =
+            tz.tokenize("$.data[?(search( @.code , \"A\") || length(value(@..x)) == 1)]")?;
+        assert_eq!(3, tokens.len());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_custom_functions() -> JsonPathResult<()> {
+        let mut functions = FunctionRegistry::new();
+        functions.register(
+            "is_valid_sku",
+            vec![FunctionType::ValueType],
+            FunctionType::LogicalType,
+            |_args| Value::Bool(true),
+        )?;
+        let tz = Tokenizer::new().with_functions(functions);
+        let tokens = tz.tokenize("$.data[?(is_valid_sku(@.sku) && length(@.sku) > 2)]")?;
+        assert_eq!(3, tokens.len());
+        // well-typedness is checked for custom functions as well
+        assert!(tz
+            .tokenize("$.data[?(is_valid_sku(@.sku) == true)]")
+            .is_err());
+        assert!(tz.tokenize("$.data[?(is_valid_sku(@.*))]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn function_registry_rejects_invalid_names() {
+        let mut functions = FunctionRegistry::new();
+        for name in ["", "isValid", "_valid", "1st", "valid-sku", "größe"] {
+            assert!(
+                functions
+                    .register(name, vec![], FunctionType::LogicalType, |_| Value::Null)
+                    .is_err(),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            Err(JsonPathError::InvalidFunction(
+                "Built-in function length() can not be overridden".to_string()
+            )),
+            functions
+                .register("length", vec![], FunctionType::ValueType, |_| Value::Null)
+                .map(|_| ())
+        );
+        assert!(functions
+            .register("is_valid_2", vec![], FunctionType::LogicalType, |_| {
+                Value::Null
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn tokenizer_reports_position_of_unknown_functions() {
+        let tz = Tokenizer::new();
+        assert_eq!(
+            Err(JsonPathError::InvalidJsonPath(
+                "Unknown function: is_valid_sku".to_string(),
+                9
+            )),
+            tz.tokenize("$.data[?(is_valid_sku(@.sku))]")
+        );
+    }
+
+    #[test]
+    fn tokenizer_reports_error_for_ill_typed_functions() {
+        let tz = Tokenizer::new();
+        // unknown function
+        assert!(tz.tokenize("$.data[?(foo(@.tags) > 2)]").is_err());
+        // wrong number of arguments
+        assert!(tz.tokenize("$.data[?(length(@.tags, @.x) > 2)]").is_err());
+        assert!(tz.tokenize("$.data[?(count() > 2)]").is_err());
+        // non-singular query as ValueType argument
+        assert!(tz.tokenize("$.data[?(length(@.*) > 2)]").is_err());
+        // literal as NodesType argument
+        assert!(tz.tokenize("$.data[?(count(1) > 2)]").is_err());
+        // LogicalType result is not comparable
+        assert!(tz.tokenize("$.data[?(match(@.a, 'a') == true)]").is_err());
+        // ValueType result is not a test expression
+        assert!(tz.tokenize("$.data[?(length(@.a))]").is_err());
+        assert!(tz.tokenize("$.data[?(!value(@.a))]").is_err());
+        // invalid regular expression literal
+        assert!(tz.tokenize("$.data[?(match(@.a, '(a'))]").is_err());
+        // functions are not path segments
+        assert!(tz.tokenize("$.data.length()").is_err());
+    }
+
+    #[test]
+    fn tokenizer_supports_placeholders() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let tokens = tz.tokenize("$.users[?(@.id == $id && @.role in $roles)][$field]")?;
+        let placeholder = |name: &str, expected| {
+            Expression::Placeholder(PlaceholderToken {
+                name: name.to_string(),
+                expected,
+            })
+        };
+        let expected = vec![
+            Token::root('$'),
+            Token::property("users".to_string()),
+            Token::Filter(Expression::CompareExpr {
+                op: Comparator::AND,
+                left: Box::new(Expression::CompareExpr {
+                    op: Comparator::Eq,
+                    left: Box::new(Expression::JsonQuery(vec![
+                        Token::root('@'),
+                        Token::property("id".to_string()),
+                    ])),
+                    right: Box::new(placeholder("id", PlaceholderType::Any)),
+                }),
+                right: Box::new(Expression::CompareExpr {
+                    op: Comparator::IN,
+                    left: Box::new(Expression::JsonQuery(vec![
+                        Token::root('@'),
+                        Token::property("role".to_string()),
+                    ])),
+                    right: Box::new(placeholder("roles", PlaceholderType::Array)),
+                }),
+            }),
+            Token::Placeholder(PlaceholderToken {
+                name: "field".to_string(),
+                expected: PlaceholderType::Selector,
+            }),
+        ];
+        assert_eq!(expected, tokens);
+        // the root document can still be queried in filters
+        assert!(tz.tokenize("$.users[?(@.id == $.id)]").is_ok());
+        assert!(tz.tokenize("$.users[?(@.id == $['id'])]").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_reports_error_for_invalid_placeholders() {
+        let tz = Tokenizer::new();
+        assert!(tz.tokenize("$.users[$1]").is_err());
+        assert!(tz.tokenize("$.users[$]").is_err());
+        assert!(tz.tokenize("$.users[$id").is_err());
+    }
+
     #[test]
     fn tokenizer_supports_filter_with_parenthesis() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(!(@.id empty true && @.id < 100))]");
         assert!(tokens.is_ok());
         Ok(())
@@ -784,9 +1301,271 @@ mod test {
 
     #[test]
     fn tokenizer_supports_filter_with_parenthesis2() -> JsonPathResult<()> {
-        let tz = Tokenizer {};
+        let tz = Tokenizer::new();
         let tokens = tz.tokenize("$.data[?(!(@.id empty true) || (@.msg empty true))]");
         assert!(tokens.is_ok());
         Ok(())
     }
+
+    #[test]
+    fn tokenizer_supports_filter_without_parenthesis() -> JsonPathResult<()> {
+        let tz = Tokenizer::new();
+        assert_eq!(
+            tz.tokenize("$.data[?(@.id > 1)]")?,
+            tz.tokenize("$.data[?@.id > 1]")?
+        );
+        assert_eq!(
+            tz.tokenize("$.data[?(@.id > 1 && @.msg)]")?,
+            tz.tokenize("$.data[? @.id > 1 && @.msg ]")?
+        );
+        assert!(tz.tokenize("$.data[?(@.id) || @.msg]").is_ok());
+        assert!(tz.tokenize("$.data[?@.id > 1").is_err());
+        assert!(tz.tokenize("$.data[?]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_filter_modes() -> JsonPathResult<()> {
+        let filter = |tz: &Tokenizer| tz.tokenize("$[?@.id]").map(|mut tokens| tokens.remove(1));
+        let expression =
+            Expression::JsonQuery(vec![Token::root('@'), Token::property("id".to_string())]);
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        assert_eq!(Token::Filter(expression.clone()), filter(&tz)?);
+        // filters test the current node itself by default
+        let tz = Tokenizer::new();
+        assert_eq!(Token::Predicate(expression), filter(&tz)?);
+        match &tz.tokenize("$['a', ?@.id]")?[1] {
+            Token::Union(selectors) => assert!(matches!(selectors[1], Selector::Predicate(_))),
+            token => panic!("expect an union token, got {:?}", token),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_filter_in_union() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let tokens = tz.tokenize("$['a', ?@.x, *, 0, $key]")?;
+        match &tokens[1] {
+            Token::Union(selectors) => {
+                assert_eq!(5, selectors.len());
+                assert_eq!(Selector::Name("a".to_string()), selectors[0]);
+                assert!(matches!(selectors[1], Selector::Filter(_)));
+                assert_eq!(Selector::Wildcard, selectors[2]);
+                assert_eq!(Selector::Index(0), selectors[3]);
+                assert!(matches!(selectors[4], Selector::Placeholder(_)));
+            }
+            token => panic!("expect an union token, got {:?}", token),
+        }
+        assert!(tz.tokenize("$[?(@.x), 'a']").is_ok());
+        assert!(tz.tokenize("$['a',]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_mixed_union() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let tokens = tz.tokenize("$.a[0, 'name', 2:4, *, ?@.x]")?;
+        match &tokens[2] {
+            Token::Union(selectors) => {
+                assert_eq!(5, selectors.len());
+                assert_eq!(Selector::Index(0), selectors[0]);
+                assert_eq!(Selector::Name("name".to_string()), selectors[1]);
+                assert_eq!(
+                    Selector::Slice(ArraySlice::new(Some(2), Some(4), None)),
+                    selectors[2]
+                );
+                assert_eq!(Selector::Wildcard, selectors[3]);
+                assert!(matches!(selectors[4], Selector::Filter(_)));
+            }
+            token => panic!("expect an union token, got {:?}", token),
+        }
+        assert_eq!(
+            Token::Union(vec![
+                Selector::Slice(ArraySlice::new(None, Some(1), None)),
+                Selector::Slice(ArraySlice::new(Some(-1), None, Some(-1))),
+            ]),
+            tz.tokenize("$[:1, -1::-1]")?[1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_decodes_escapes_in_strings() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let name = |path: &str| match tz.tokenize(path).map(|mut tokens| tokens.remove(1)) {
+            Ok(Token::Property(PropertyPathToken { mut properties })) => Ok(properties.remove(0)),
+            Ok(token) => panic!("expect a property token, got {:?}", token),
+            Err(e) => Err(e),
+        };
+        assert_eq!(Ok("it's".to_string()), name(r"$['it\'s']"));
+        assert_eq!(Ok("say \"hi\"".to_string()), name(r#"$["say \"hi\""]"#));
+        assert_eq!(Ok("a\nb\tc\\/".to_string()), name(r"$['a\nb\tc\\\/']"));
+        assert_eq!(Ok("\u{8}\u{c}\r".to_string()), name(r"$['\b\f\r']"));
+        assert_eq!(Ok("é€".to_string()), name(r"$['é€']"));
+        assert_eq!(Ok("😀".to_string()), name(r"$['😀']"));
+        assert_eq!(Ok("]'".to_string()), name(r"$[']\'']"));
+
+        let tokens = tz.tokenize(r"$[?@.msg == 'it\'s\n']")?;
+        let expected = Token::Filter(Expression::CompareExpr {
+            op: Comparator::Eq,
+            left: Box::new(Expression::JsonQuery(vec![
+                Token::root('@'),
+                Token::property("msg".to_string()),
+            ])),
+            right: Box::new(Expression::Literal(Value::String("it's\n".to_string()))),
+        });
+        assert_eq!(expected, tokens[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_reports_position_of_invalid_escapes() {
+        let tz = Tokenizer::new();
+        let error =
+            |msg: &str, pos: usize| Err(JsonPathError::InvalidJsonPath(msg.to_string(), pos));
+        assert_eq!(
+            error(r"Invalid escape sequence: \x", 5),
+            tz.tokenize(r"$['ab\x']")
+        );
+        assert_eq!(
+            error("Invalid unicode escape sequence, expect 4 hex digits.", 3),
+            tz.tokenize(r"$['\u12g4']")
+        );
+        assert_eq!(
+            error("Unpaired surrogate in unicode escape sequence.", 3),
+            tz.tokenize(r"$['\uD83Dx']")
+        );
+        assert_eq!(
+            error("Unpaired surrogate in unicode escape sequence.", 3),
+            tz.tokenize(r"$['\uDE00']")
+        );
+        assert_eq!(
+            error(r"Invalid escape sequence: \q", 14),
+            tz.tokenize(r"$[?@.msg == 'a\q']")
+        );
+        assert_eq!(
+            error("Unterminated string.", 11),
+            tz.tokenize("$['abc', 'd")
+        );
+    }
+
+    #[test]
+    fn tokenizer_supports_json_literals() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let literal = |literal: &str| -> JsonPathResult<Value> {
+            match tz.tokenize(&format!("$[?@ == {}]", literal))?.remove(1) {
+                Token::Filter(Expression::CompareExpr { right, .. }) => match *right {
+                    Expression::Literal(v) => Ok(v),
+                    e => panic!("expect a literal, got {:?}", e),
+                },
+                token => panic!("expect a filter token, got {:?}", token),
+            }
+        };
+        assert_eq!(Value::Null, literal("null")?);
+        assert_eq!(json!(1000000.0), literal("1e6")?);
+        assert_eq!(json!(-0.025), literal("-2.5E-2")?);
+        assert_eq!(json!(120.0), literal("1.2e+2")?);
+        assert_eq!(json!(-3), literal("-3")?);
+        assert_eq!(json!([]), literal("[ ]")?);
+        assert_eq!(
+            json!([["a", "b"], [], [null, true]]),
+            literal(r#"[["a","b"], [], [null, true]]"#)?
+        );
+        assert_eq!(json!({}), literal("{ }")?);
+        assert_eq!(
+            json!({"x": 1, "y": {"z": [2, "]"]}}),
+            literal(r#"{"x": 1, 'y': {"z": [2, ']']}}"#)?
+        );
+
+        for invalid in [
+            "nul",
+            "1e",
+            "01",
+            "1.",
+            "-.5",
+            "[1,]",
+            "[1 2]",
+            "{x: 1}",
+            r#"{"x" 1}"#,
+            r#"{"x": 1,}"#,
+        ] {
+            assert!(literal(invalid).is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tokenizer_supports_arithmetic() -> JsonPathResult<()> {
+        let tz = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+        let query = |name: &str| {
+            Box::new(Expression::JsonQuery(vec![
+                Token::root('@'),
+                Token::property(name.to_string()),
+            ]))
+        };
+        let binary = |op, left, right| Box::new(Expression::CompareExpr { op, left, right });
+        let literal = |v: i32| Box::new(Expression::Literal(json!(v)));
+
+        // * binds tighter than +, which binds tighter than comparisons
+        let tokens = tz.tokenize("$[?@.a + @.b * 2 > -@.c - 1]")?;
+        let expected = Token::Filter(*binary(
+            Comparator::Gt,
+            binary(
+                Comparator::Add,
+                query("a"),
+                binary(Comparator::Mul, query("b"), literal(2)),
+            ),
+            binary(
+                Comparator::Sub,
+                Box::new(Expression::Negate(query("c"))),
+                literal(1),
+            ),
+        ));
+        assert_eq!(expected, tokens[1]);
+
+        // operators of the same binding power are left associative
+        let tokens = tz.tokenize("$[?(@.a - 1 - 2 == @.a % 3 / 4)]")?;
+        let expected = Token::Filter(*binary(
+            Comparator::Eq,
+            binary(
+                Comparator::Sub,
+                binary(Comparator::Sub, query("a"), literal(1)),
+                literal(2),
+            ),
+            binary(
+                Comparator::Div,
+                binary(Comparator::Rem, query("a"), literal(3)),
+                literal(4),
+            ),
+        ));
+        assert_eq!(expected, tokens[1]);
+
+        assert!(tz.tokenize("$[?length(@.a) + 1 > 2]").is_ok());
+        assert!(tz.tokenize("$[?length(@.a + 'x') > 2]").is_ok());
+        assert!(tz.tokenize("$[?match(@.a, 'x') + 1 > 2]").is_err());
+        assert!(tz.tokenize("$[?@.a + > 2]").is_err());
+
+        // arithmetic operators other than `-` end a dot-notated name
+        let tokens = tz.tokenize("$[?(@.a*2 == @.a+1)]")?;
+        let expected = Token::Filter(*binary(
+            Comparator::Eq,
+            binary(Comparator::Mul, query("a"), literal(2)),
+            binary(Comparator::Add, query("a"), literal(1)),
+        ));
+        assert_eq!(expected, tokens[1]);
+        let tokens = tz.tokenize("$[?(@.a/2 > @.a%3)]")?;
+        let expected = Token::Filter(*binary(
+            Comparator::Gt,
+            binary(Comparator::Div, query("a"), literal(2)),
+            binary(Comparator::Rem, query("a"), literal(3)),
+        ));
+        assert_eq!(expected, tokens[1]);
+        let tokens = tz.tokenize("$[?(@.a-b > 1)]")?;
+        let expected = Token::Filter(*binary(Comparator::Gt, query("a-b"), literal(1)));
+        assert_eq!(expected, tokens[1]);
+        // such names are selected with the bracket notation
+        assert!(tz.tokenize("$.a+b").is_err());
+        assert!(tz.tokenize("$['a+b']").is_ok());
+        Ok(())
+    }
 }
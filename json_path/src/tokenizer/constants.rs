@@ -11,9 +11,6 @@ pub const CLOSE_BRACE: char = '}';
 pub const WILDCARD: char = '*';
 pub const PERIOD: char = '.';
 pub const SPACE: char = ' ';
-pub const TAB: char = '\t';
-pub const CR: char = '\r';
-pub const LF: char = '\n';
 pub const BEGIN_FILTER: char = '?';
 pub const COMMA: char = ',';
 pub const SPLIT: char = ':';
@@ -22,3 +19,4 @@ pub const SINGLE_QUOTE: char = '\'';
 pub const DOUBLE_QUOTE: char = '"';
 pub const ESCAPE: char = '\\';
 pub const NOT: char = '!';
+pub const SLASH: char = '/';
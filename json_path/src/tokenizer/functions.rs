@@ -0,0 +1,124 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{JsonPathError, JsonPathResult};
+
+use super::{Function, FunctionType};
+
+type FunctionImpl = dyn Fn(&[Value]) -> Value + Send + Sync;
+
+/// A user defined function that can be used within filter expressions.
+///
+/// Arguments are passed to the closure as json values: a `ValueType` argument is the value itself
+/// (`null` if there is nothing), a `LogicalType` argument is a boolean, and a `NodesType` argument
+/// is an array of the selected nodes. A `LogicalType` result is tested for truthiness, and a
+/// `NodesType` result should be an array of nodes.
+#[derive(Clone)]
+pub struct CustomFunction {
+    name: String,
+    parameters: Vec<FunctionType>,
+    result_type: FunctionType,
+    function: Arc<FunctionImpl>,
+}
+
+impl CustomFunction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parameters(&self) -> &[FunctionType] {
+        &self.parameters
+    }
+
+    pub fn result_type(&self) -> FunctionType {
+        self.result_type
+    }
+
+    pub fn call(&self, args: &[Value]) -> Value {
+        (self.function)(args)
+    }
+}
+
+impl Debug for CustomFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomFunction")
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .field("result_type", &self.result_type)
+            .finish()
+    }
+}
+
+impl PartialEq for CustomFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.parameters == other.parameters
+            && self.result_type == other.result_type
+            && Arc::ptr_eq(&self.function, &other.function)
+    }
+}
+
+/// User defined functions that the tokenizer resolves function names against.
+///
+/// Function names should be lower case ascii letters, digits or `_`, starting with a letter.
+/// Built-in functions like `length()` can not be overridden.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Arc<CustomFunction>>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> FunctionRegistry {
+        FunctionRegistry {
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Register a function by its name, argument types and result type.
+    ///
+    /// Names that can not be parsed within a jsonpath, and names of built-in functions, are
+    /// rejected.
+    pub fn register<F>(
+        &mut self,
+        name: &str,
+        parameters: Vec<FunctionType>,
+        result_type: FunctionType,
+        function: F,
+    ) -> JsonPathResult<&mut Self>
+    where
+        F: Fn(&[Value]) -> Value + Send + Sync + 'static,
+    {
+        if !is_function_name(name) {
+            return Err(JsonPathError::InvalidFunction(format!(
+                "Function name should be lower case ascii letters, digits or _, starting with a letter: {:?}",
+                name
+            )));
+        }
+        if Function::from_name(name).is_some() {
+            return Err(JsonPathError::InvalidFunction(format!(
+                "Built-in function {}() can not be overridden",
+                name
+            )));
+        }
+        let custom = CustomFunction {
+            name: name.to_string(),
+            parameters,
+            result_type,
+            function: Arc::new(function),
+        };
+        self.functions.insert(name.to_string(), Arc::new(custom));
+        Ok(self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<CustomFunction>> {
+        self.functions.get(name).cloned()
+    }
+}
+
+/// whether the tokenizer reads the name as a function name, see `Tokenizer::peek_function_name`
+fn is_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
@@ -1,9 +1,9 @@
-use serde_json::{json, Value};
+use serde_json::{Number, Value};
 
 use crate::{JsonPathError, JsonPathResult};
 
 use super::{
-    constants::{DOUBLE_QUOTE, ESCAPE, MINUS, PERIOD, SINGLE_QUOTE},
+    constants::{DOUBLE_QUOTE, ESCAPE, MINUS, PERIOD, SINGLE_QUOTE, SLASH},
     TokenStream,
 };
 pub trait PeekableExt {
@@ -21,16 +21,16 @@ pub trait PeekableExt {
 
     fn peek_significant_and_move_on(&mut self) -> Option<char>;
 
-    /**
-     * peek next word separated by spaces, e.g. " hello "
-     */
-    fn peek_next_word(&mut self) -> Option<String>;
-
     fn read_quoted_string(&mut self) -> JsonPathResult<String>;
 
     fn peek_matches_ignore_case(&mut self, pattern: &str) -> JsonPathResult<bool>;
 
     fn read_number(&mut self) -> JsonPathResult<Value>;
+
+    /**
+     * read a regular expression literal and its flags, e.g. /foo.*?/i
+     */
+    fn read_regex(&mut self) -> JsonPathResult<(String, String)>;
 }
 
 impl<'a> PeekableExt for TokenStream<'a> {
@@ -82,7 +82,7 @@ impl<'a> PeekableExt for TokenStream<'a> {
         let mut chars = pattern.chars();
         loop {
             match (chars.next(), self.peek()) {
-                (Some(l), Some(r)) if l.to_ascii_lowercase() == r.to_ascii_lowercase() => {
+                (Some(l), Some(r)) if l.eq_ignore_ascii_case(r) => {
                     self.advance_cursor();
                 }
                 (None, _) => break,
@@ -95,52 +95,26 @@ impl<'a> PeekableExt for TokenStream<'a> {
         Ok(true)
     }
 
-    fn peek_next_word(&mut self) -> Option<String> {
-        let mut w = String::new();
-        while let Some(c) = self.peek() {
-            if !c.is_whitespace() {
-                w.push(*c); // append to word for any non space chars
-            } else if !w.is_empty() {
-                break; // when any space
-            }
-            self.advance_cursor();
-        }
-        Some(w).filter(|w| !w.is_empty())
-    }
-
     fn read_number(&mut self) -> JsonPathResult<Value> {
+        self.drop_while(|c| c.is_whitespace());
         let mut w = String::new();
         while let Some(c) = self.peek() {
-            if c.is_ascii_digit() || *c == PERIOD || *c == MINUS {
-                w.push(*c); // append to word for any non space chars
-            } else if !w.is_empty() {
-                break; // when any space
+            // a sign is only allowed at the start of the number or of its exponent
+            let sign = if w.is_empty() || w.ends_with(['e', 'E']) {
+                *c == MINUS || (*c == '+' && !w.is_empty())
+            } else {
+                false
+            };
+            if c.is_ascii_digit() || *c == PERIOD || *c == 'e' || *c == 'E' || sign {
+                w.push(*c);
+                self.next();
+            } else {
+                break;
             }
-            self.next();
         }
-        if w.is_empty() {
-            return Err(JsonPathError::InvalidJsonPath(
-                "Expect number.".to_string(),
-                0,
-            ));
-        }
-        let n = if w.contains('.') {
-            let f = w.parse::<f64>().map_err(|_e| {
-                JsonPathError::InvalidJsonPath("expect float number.".to_string(), 0)
-            })?;
-            json!(f)
-        } else if w.contains(MINUS) {
-            let i = w.parse::<i64>().map_err(|_e| {
-                JsonPathError::InvalidJsonPath("expect float number.".to_string(), 0)
-            })?;
-            json!(i)
-        } else {
-            let u = w.parse::<u64>().map_err(|_e| {
-                JsonPathError::InvalidJsonPath("expect u64 number.".to_string(), 0)
-            })?;
-            json!(u)
-        };
-        Ok(n)
+        w.parse::<Number>()
+            .map(Value::Number)
+            .map_err(|_e| JsonPathError::InvalidJsonPath(format!("Invalid number: {:?}", w), 0))
     }
 
     fn read_quoted_string(&mut self) -> JsonPathResult<String> {
@@ -149,28 +123,132 @@ impl<'a> PeekableExt for TokenStream<'a> {
             _x => {
                 return Err(JsonPathError::InvalidJsonPath(
                     "Expect quoted string.".to_string(),
-                    self.cursor(),
+                    0,
                 ));
             }
         };
 
         let mut s = String::new();
+        loop {
+            match self.peek_nth(0).copied() {
+                None => {
+                    return Err(JsonPathError::InvalidJsonPath(
+                        "Unterminated string.".to_string(),
+                        0,
+                    ))
+                }
+                Some(c) if c == quote => {
+                    // end of string
+                    self.next();
+                    break;
+                }
+                Some(ESCAPE) => s.push(read_escape(self)?),
+                Some(c) => {
+                    self.next();
+                    s.push(c);
+                }
+            }
+        }
+
+        Ok(s)
+    }
+
+    fn read_regex(&mut self) -> JsonPathResult<(String, String)> {
+        match self.next_significant() {
+            Some(SLASH) => {}
+            _ => {
+                return Err(JsonPathError::InvalidJsonPath(
+                    "Expect regular expression.".to_string(),
+                    0,
+                ));
+            }
+        }
+
+        let mut pattern = String::new();
+        let mut closed = false;
         let mut in_escape = false;
         for c in self.by_ref() {
             if in_escape {
-                s.push(c);
+                // an escaped slash is part of the pattern, other escapes are kept for the regex
+                if c != SLASH {
+                    pattern.push(ESCAPE);
+                }
+                pattern.push(c);
                 in_escape = false;
             } else if c == ESCAPE {
-                s.push(c);
                 in_escape = true;
-            } else if c == quote {
-                // end of string
+            } else if c == SLASH {
+                closed = true;
                 break;
             } else {
-                s.push(c);
+                pattern.push(c);
             }
         }
+        if !closed {
+            return Err(JsonPathError::InvalidJsonPath(
+                "Unterminated regular expression.".to_string(),
+                0,
+            ));
+        }
 
-        Ok(s)
+        let mut flags = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphabetic() {
+                flags.push(*c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        Ok((pattern, flags))
+    }
+}
+
+/// decode the JSON escape sequence at the start of the stream, e.g. \n or \u00e9, the stream is
+/// left at the backslash when the escape sequence is invalid
+fn read_escape(stream: &mut TokenStream<'_>) -> JsonPathResult<char> {
+    let invalid = |msg: &str| Err(JsonPathError::InvalidJsonPath(msg.to_string(), 0));
+    let (c, len) = match stream.peek_nth(1).copied() {
+        Some('b') => ('\u{0008}', 2),
+        Some('f') => ('\u{000C}', 2),
+        Some('n') => ('\n', 2),
+        Some('r') => ('\r', 2),
+        Some('t') => ('\t', 2),
+        Some(c @ ('/' | ESCAPE | SINGLE_QUOTE | DOUBLE_QUOTE)) => (c, 2),
+        Some('u') => match peek_hex(stream, 2) {
+            Some(high @ 0xD800..=0xDBFF) => {
+                // a high surrogate has to be followed by an escaped low surrogate
+                let low = match (stream.peek_nth(6).copied(), stream.peek_nth(7).copied()) {
+                    (Some(ESCAPE), Some('u')) => peek_hex(stream, 8),
+                    _ => None,
+                };
+                match low {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                        (char::from_u32(code).unwrap_or_default(), 12)
+                    }
+                    _ => return invalid("Unpaired surrogate in unicode escape sequence."),
+                }
+            }
+            Some(0xDC00..=0xDFFF) => {
+                return invalid("Unpaired surrogate in unicode escape sequence.")
+            }
+            Some(code) => (char::from_u32(code).unwrap_or_default(), 6),
+            None => return invalid("Invalid unicode escape sequence, expect 4 hex digits."),
+        },
+        Some(c) => return invalid(&format!("Invalid escape sequence: \\{}", c)),
+        None => return invalid("Unterminated string."),
+    };
+    for _ in 0..len {
+        stream.next();
     }
+    Ok(c)
+}
+
+/// the value of the 4 hex digits at the offset of the stream
+fn peek_hex(stream: &mut TokenStream<'_>, offset: usize) -> Option<u32> {
+    (offset..offset + 4).try_fold(0, |code, n| {
+        let digit = stream.peek_nth(n)?.to_digit(16)?;
+        Some(code * 16 + digit)
+    })
 }
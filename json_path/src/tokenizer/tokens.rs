@@ -1,29 +1,134 @@
+use std::sync::Arc;
+
+use regex::{Regex, RegexBuilder};
 use serde_json::Value;
 
 use crate::{JsonPathError, JsonPathResult};
 
-use super::constants::{COMMA, SPLIT};
+use super::{
+    constants::{COMMA, SPLIT},
+    CustomFunction,
+};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Root(RootPathToken),
     Property(PropertyPathToken),
-    ArrayIndex { indices: Vec<i32> },
+    ArrayIndex {
+        indices: Vec<i32>,
+    },
     ArraySlice(ArraySlice),
+    /// tests the current node itself, for filters parsed in [`FilterMode::Compat`]
     Predicate(Expression),
+    /// selects the members of an array or object for which the expression is true
+    Filter(Expression),
     Function(FunctionPathToken),
     Scan(ScanPathToken),
     Wildcard,
+    Placeholder(PlaceholderToken),
+    /// different kinds of selectors within one pair of square brackets, e.g. [0, 'name', ?@.x]
+    Union(Vec<Selector>),
+}
+
+/// A selector within square brackets, see RFC 9535 section 2.3.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Name(String),
+    Index(i32),
+    Slice(ArraySlice),
+    Wildcard,
+    Filter(Expression),
+    /// a filter parsed in [`FilterMode::Compat`], which tests the current node itself
+    Predicate(Expression),
+    Placeholder(PlaceholderToken),
+}
+
+/// How filter selectors like `[?(@.price < 10)]` are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// a filter selects the members of an array or object for which the expression is true,
+    /// as defined by RFC 9535, e.g. `$.data[?@.msg]`
+    Standard,
+    /// a filter tests the current node itself, so that filtering an array takes a wildcard
+    /// first, e.g. `$.data[*][?(@.msg)]`
+    #[default]
+    Compat,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ArraySlice {
+/// An array slice `[start:end:step]`, see RFC 9535 section 2.3.4.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArraySlice {
     // inclusive
-    From(i32),
+    pub start: Option<i32>,
     // exclusive
-    To(i32),
-    // inclusive, exclusive
-    Between(i32, i32),
+    pub end: Option<i32>,
+    // 1 by default, a negative step selects elements in reverse order
+    pub step: Option<i32>,
+}
+
+impl ArraySlice {
+    pub fn new(start: Option<i32>, end: Option<i32>, step: Option<i32>) -> ArraySlice {
+        ArraySlice { start, end, step }
+    }
+
+    /// Parse a slice like `1:5:2`, all of start, end and step are optional.
+    pub fn parse(expr: &str) -> JsonPathResult<ArraySlice> {
+        let parts = expr
+            .split(SPLIT)
+            .map(|part| match part.trim() {
+                "" => Ok(None),
+                part => Token::as_i32(part).map(Some),
+            })
+            .collect::<JsonPathResult<Vec<Option<i32>>>>()?;
+        match parts[..] {
+            [start, end] => Ok(ArraySlice::new(start, end, None)),
+            [start, end, step] => Ok(ArraySlice::new(start, end, step)),
+            _ => Err(JsonPathError::InvalidJsonPath(
+                format!("Invalid array slice: {}", expr),
+                0,
+            )),
+        }
+    }
+
+    /// The indices selected from an array of the given length, in the order they are selected.
+    /// Negative start and end count from the end of the array, and are clamped to the array
+    /// bounds. A zero step selects nothing.
+    pub fn indices(&self, len: usize) -> impl DoubleEndedIterator<Item = usize> {
+        let len = len as i64;
+        let step = self.step.unwrap_or(1) as i64;
+        let normalize = |i: i32| {
+            let i = i as i64;
+            if i >= 0 {
+                i
+            } else {
+                len + i
+            }
+        };
+        let (first, count) = if step > 0 {
+            let lower = self.start.map(normalize).unwrap_or(0).clamp(0, len);
+            let upper = self.end.map(normalize).unwrap_or(len).clamp(0, len);
+            let count = if lower < upper {
+                (upper - lower + step - 1) / step
+            } else {
+                0
+            };
+            (lower, count)
+        } else if step < 0 {
+            let upper = self.start.map(normalize).unwrap_or(len - 1);
+            let upper = upper.clamp(-1, len - 1);
+            let lower = self.end.map(normalize).unwrap_or(-len - 1);
+            let lower = lower.clamp(-1, len - 1);
+            let count = if lower < upper {
+                (upper - lower - step - 1) / -step
+            } else {
+                0
+            };
+            (upper, count)
+        } else {
+            (0, 0)
+        };
+        (0..count).map(move |k| (first + k * step) as usize)
+    }
 }
 
 impl Token {
@@ -52,51 +157,228 @@ impl Token {
     }
 
     pub fn array_slice(expr: String) -> JsonPathResult<Token> {
-        let parts: Vec<&str> = expr.split(SPLIT).collect();
-        if !parts.len() == 2 {
-            return Err(JsonPathError::InvalidJsonPath(
-                format!("Invalid array slice: {}", expr),
-                0,
-            ));
+        ArraySlice::parse(&expr).map(Token::ArraySlice)
+    }
+
+    /// The token for the selectors within one pair of square brackets. Selectors of the same kind
+    /// are combined into the existing tokens, e.g. ['a','b'] into a property token, any other
+    /// combination of selectors is kept as an union.
+    pub fn selection(mut selectors: Vec<Selector>) -> Token {
+        if selectors.iter().all(|s| matches!(s, Selector::Name(_))) {
+            let properties = selectors
+                .into_iter()
+                .filter_map(|s| match s {
+                    Selector::Name(name) => Some(name),
+                    _ => None,
+                })
+                .collect();
+            return Token::properties(properties);
+        }
+        if selectors.iter().all(|s| matches!(s, Selector::Index(_))) {
+            let indices = selectors
+                .into_iter()
+                .filter_map(|s| match s {
+                    Selector::Index(index) => Some(index),
+                    _ => None,
+                })
+                .collect();
+            return Token::ArrayIndex { indices };
+        }
+        if selectors.len() > 1 {
+            return Token::Union(selectors);
+        }
+        match selectors.pop() {
+            Some(Selector::Slice(slice)) => Token::ArraySlice(slice),
+            Some(Selector::Wildcard) => Token::Wildcard,
+            Some(Selector::Filter(expression)) => Token::Filter(expression),
+            Some(Selector::Predicate(expression)) => Token::Predicate(expression),
+            Some(Selector::Placeholder(placeholder)) => Token::Placeholder(placeholder),
+            // names and indices are combined above
+            _ => unreachable!(),
         }
-        let array_slice = match (parts[0].trim(), parts[1].trim()) {
-            ("", "") => {
-                return Err(JsonPathError::InvalidJsonPath(
-                    format!("Invalid array slice: {}", expr),
-                    0,
-                ))
-            }
-            (f, "") if !f.is_empty() => ArraySlice::From(Self::as_i32(f)?),
-            ("", t) if !t.is_empty() => ArraySlice::To(Self::as_i32(t)?),
-            (f, t) => ArraySlice::Between(Self::as_i32(f)?, Self::as_i32(t)?),
-        };
-        Ok(Token::ArraySlice(array_slice))
     }
 
-    fn as_i32(v: &str) -> JsonPathResult<i32> {
+    /// Whether this token selects at most one node, i.e. a single property or array index.
+    pub fn is_singular(&self) -> bool {
+        match self {
+            Token::Root(_) => true,
+            Token::Property(PropertyPathToken { properties }) => properties.len() == 1,
+            Token::ArrayIndex { indices } => indices.len() == 1,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn as_i32(v: &str) -> JsonPathResult<i32> {
         v.trim()
             .parse::<i32>()
             .map_err(|_e| JsonPathError::InvalidJsonPath("Invalid array index.".to_string(), 0))
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// The shape of a query result, known from the tokens before any evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultShape {
+    /// a single value, or null when nothing is selected
+    Scalar,
+    /// an array of all selected values
+    Array,
+}
+
+/// The shape of the result of the given tokens, a jsonpath returns an array if any of its
+/// selectors may select more than one node. Multiple properties selected by the last token are
+/// merged into one object, e.g. `$['a','b']`.
+pub fn result_shape(tokens: &[Token]) -> ResultShape {
+    let array = tokens.iter().enumerate().any(|(i, token)| match token {
+        Token::Property(PropertyPathToken { properties }) => {
+            properties.len() > 1 && i + 1 < tokens.len()
+        }
+        Token::ArrayIndex { indices } => indices.len() > 1,
+        Token::ArraySlice(_)
+        | Token::Filter(_)
+        | Token::Scan(_)
+        | Token::Wildcard
+        | Token::Union(_) => true,
+        _ => false,
+    });
+    if array {
+        ResultShape::Array
+    } else {
+        ResultShape::Scalar
+    }
+}
+
+/// Whether the given tokens select at most one node, i.e. they only consist of single property
+/// names, array indices and placeholders.
+pub fn is_definite(tokens: &[Token]) -> bool {
+    tokens
+        .iter()
+        .all(|token| token.is_singular() || matches!(token, Token::Placeholder(_)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RootPathToken {
     pub root_path_char: char,
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PropertyPathToken {
     pub properties: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
+/// A named placeholder, e.g. `$id`, which is bound to a value when the jsonpath is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceholderToken {
+    /// name of the placeholder, without the leading `$`
+    pub name: String,
+    /// the type of value that can be bound, as far as it can be inferred from the jsonpath
+    pub expected: PlaceholderType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaceholderType {
+    Any,
+    Array,
+    Number,
+    Bool,
+    /// a property name or an array index
+    Selector,
+}
+
+impl PlaceholderType {
+    /// Check if the given value can be bound to a placeholder of this type.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self {
+            PlaceholderType::Any => true,
+            PlaceholderType::Array => value.is_array(),
+            PlaceholderType::Number => value.is_number(),
+            PlaceholderType::Bool => value.is_boolean(),
+            PlaceholderType::Selector => value.is_string() || value.is_i64(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PredicatePathToken {}
-#[derive(Debug, PartialEq)]
-pub struct FunctionPathToken {}
-#[derive(Debug, PartialEq)]
+/// A function call within a filter expression, e.g. `length(@.tags)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionPathToken {
+    pub function: Function,
+    pub args: Vec<Expression>,
+}
+
+/// Types of function parameters and results, see RFC 9535 section 2.4.1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionType {
+    /// A json value, or nothing.
+    ValueType,
+    /// A boolean, `true` or `false`.
+    LogicalType,
+    /// A list of nodes selected by a jsonpath query.
+    NodesType,
+}
+
+/// Function extensions that can be used within filter expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Function {
+    /// `length(ValueType) -> ValueType`, length of a string, array or object.
+    Length,
+    /// `count(NodesType) -> ValueType`, number of nodes.
+    Count,
+    /// `match(ValueType, ValueType) -> LogicalType`, string matches the regular expression entirely.
+    Match,
+    /// `search(ValueType, ValueType) -> LogicalType`, string contains a match of the regular expression.
+    Search,
+    /// `value(NodesType) -> ValueType`, value of the only node.
+    Value,
+    /// A function registered in a [`FunctionRegistry`](super::FunctionRegistry).
+    Custom(Arc<CustomFunction>),
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "length" => Some(Function::Length),
+            "count" => Some(Function::Count),
+            "match" => Some(Function::Match),
+            "search" => Some(Function::Search),
+            "value" => Some(Function::Value),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Length => "length",
+            Function::Count => "count",
+            Function::Match => "match",
+            Function::Search => "search",
+            Function::Value => "value",
+            Function::Custom(f) => f.name(),
+        }
+    }
+
+    pub fn parameters(&self) -> &[FunctionType] {
+        match self {
+            Function::Length => &[FunctionType::ValueType],
+            Function::Count | Function::Value => &[FunctionType::NodesType],
+            Function::Match | Function::Search => {
+                &[FunctionType::ValueType, FunctionType::ValueType]
+            }
+            Function::Custom(f) => f.parameters(),
+        }
+    }
+
+    pub fn result_type(&self) -> FunctionType {
+        match self {
+            Function::Length | Function::Count | Function::Value => FunctionType::ValueType,
+            Function::Match | Function::Search => FunctionType::LogicalType,
+            Function::Custom(f) => f.result_type(),
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScanPathToken {}
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Comparator {
     Eq,
     Neq,
@@ -113,15 +395,112 @@ pub enum Comparator {
     AnyOf,
     NoneOf,
     Contains,
+    /// case-insensitive `contains`
+    IContains,
+    StartsWith,
+    EndsWith,
+    /// case-insensitive `==` for strings
+    IEq,
     SizeOf,
     Empty,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Comparator {
+    /// Whether this is an arithmetic operator, i.e. `+`, `-`, `*`, `/` or `%`.
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Comparator::Add | Comparator::Sub | Comparator::Mul | Comparator::Div | Comparator::Rem
+        )
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// A regular expression literal, e.g. `/foo.*?/i`, compiled when the jsonpath is parsed.
+///
+/// Supported flags are `i` (case insensitive), `m` (multi-line) and `s` (`.` matches `\n`).
+#[derive(Debug, Clone)]
+pub struct RegexLiteral {
+    pub pattern: String,
+    pub flags: String,
+    full_match: bool,
+    regex: Regex,
+}
+
+impl RegexLiteral {
+    pub fn new(pattern: String, flags: String) -> JsonPathResult<RegexLiteral> {
+        Self::build(pattern, flags, false)
+    }
+
+    /// A regular expression that has to match the entire string, as used by `match()`.
+    pub fn full_match(pattern: String) -> JsonPathResult<RegexLiteral> {
+        Self::build(pattern, String::new(), true)
+    }
+
+    fn build(pattern: String, flags: String, full_match: bool) -> JsonPathResult<RegexLiteral> {
+        let mut builder = match full_match {
+            true => RegexBuilder::new(&format!("^(?:{})$", pattern)),
+            false => RegexBuilder::new(&pattern),
+        };
+        for flag in flags.chars() {
+            match flag {
+                'i' => builder.case_insensitive(true),
+                'm' => builder.multi_line(true),
+                's' => builder.dot_matches_new_line(true),
+                f => {
+                    return Err(JsonPathError::InvalidJsonPath(
+                        format!("Unsupported regular expression flag: {:?}", f),
+                        0,
+                    ))
+                }
+            };
+        }
+        let regex = builder.build().map_err(|e| {
+            JsonPathError::InvalidJsonPath(format!("Invalid regular expression: {}", e), 0)
+        })?;
+        Ok(RegexLiteral {
+            pattern,
+            flags,
+            full_match,
+            regex,
+        })
+    }
+
+    /// Check if the regular expression matches the given string.
+    pub fn is_match(&self, s: &str) -> bool {
+        self.regex.is_match(s)
+    }
+}
+
+impl PartialEq for RegexLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+            && self.flags == other.flags
+            && self.full_match == other.full_match
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
+    /// a query from the current node, e.g. `@.price`
     JsonQuery(Vec<Token>),
+    /// a query from the root document, e.g. `$.limits.max`, which selects the same nodes for
+    /// every filtered node, the id is unique within the jsonpath
+    RootQuery {
+        id: usize,
+        tokens: Vec<Token>,
+    },
     Literal(Value),
+    Regex(RegexLiteral),
+    Function(FunctionPathToken),
+    Placeholder(PlaceholderToken),
     Not(Box<Expression>),
+    /// unary minus, e.g. `-@.balance`
+    Negate(Box<Expression>),
     Array(Vec<Expression>),
     CompareExpr {
         op: Comparator,
@@ -134,12 +513,12 @@ pub enum Expression {
 mod test {
     use crate::tokenizer::Token;
 
-    use crate::tokenizer::tokens::ArraySlice;
+    use crate::tokenizer::tokens::{ArraySlice, RegexLiteral};
 
     #[test]
     fn can_parse_array_slice_from() {
         assert_eq!(
-            Ok(Token::ArraySlice(ArraySlice::From(3))),
+            Ok(Token::ArraySlice(ArraySlice::new(Some(3), None, None))),
             Token::array_slice(" 3 :".to_string())
         )
     }
@@ -147,7 +526,7 @@ mod test {
     #[test]
     fn can_parse_array_slice_to() {
         assert_eq!(
-            Ok(Token::ArraySlice(ArraySlice::To(3))),
+            Ok(Token::ArraySlice(ArraySlice::new(None, Some(3), None))),
             Token::array_slice("  : 3 ".to_string())
         )
     }
@@ -155,11 +534,74 @@ mod test {
     #[test]
     fn can_parse_array_slice_between() {
         assert_eq!(
-            Ok(Token::ArraySlice(ArraySlice::Between(1, 3))),
+            Ok(Token::ArraySlice(ArraySlice::new(Some(1), Some(3), None))),
             Token::array_slice(" 1 : 3 ".to_string())
         )
     }
 
+    #[test]
+    fn can_parse_array_slice_with_step() {
+        assert_eq!(
+            Ok(Token::ArraySlice(ArraySlice::new(None, None, Some(-1)))),
+            Token::array_slice("::-1".to_string())
+        );
+        assert_eq!(
+            Ok(Token::ArraySlice(ArraySlice::new(
+                Some(1),
+                Some(5),
+                Some(2)
+            ))),
+            Token::array_slice("1:5:2".to_string())
+        );
+        assert_eq!(
+            Ok(Token::ArraySlice(ArraySlice::new(None, None, None))),
+            Token::array_slice(":".to_string())
+        );
+        assert!(Token::array_slice("1:2:3:4".to_string()).is_err());
+        assert!(Token::array_slice("1:-:3".to_string()).is_err());
+    }
+
+    #[test]
+    fn array_slice_selects_indices() {
+        let indices = |start, end, step, len| {
+            ArraySlice::new(start, end, step)
+                .indices(len)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(vec![0, 1, 2, 3, 4], indices(None, None, None, 5));
+        assert_eq!(vec![0, 2, 4], indices(None, None, Some(2), 5));
+        assert_eq!(vec![1, 4], indices(Some(1), None, Some(3), 5));
+        assert_eq!(vec![4, 3, 2, 1, 0], indices(None, None, Some(-1), 5));
+        assert_eq!(vec![4, 2, 0], indices(None, None, Some(-2), 5));
+        assert_eq!(vec![3, 2], indices(Some(-2), Some(1), Some(-1), 5));
+        assert_eq!(vec![2, 3], indices(Some(-3), Some(-1), None, 5));
+        assert_eq!(vec![0, 1], indices(Some(-10), Some(2), None, 5));
+        assert_eq!(vec![4], indices(Some(10), Some(3), Some(-1), 5));
+        assert!(indices(Some(3), Some(1), None, 5).is_empty());
+        assert!(indices(None, None, Some(0), 5).is_empty());
+        assert!(indices(None, None, Some(-1), 0).is_empty());
+    }
+
+    #[test]
+    fn can_compile_regex_literal_with_flags() {
+        let regex = RegexLiteral::new("^foo.*".to_string(), "is".to_string()).unwrap();
+        assert!(regex.is_match("FOO\nbar"));
+        assert!(!regex.is_match("bar foo"));
+    }
+
+    #[test]
+    fn can_not_compile_regex_literal_with_unknown_flags() {
+        assert!(RegexLiteral::new("foo".to_string(), "g".to_string()).is_err());
+        assert!(RegexLiteral::new("(foo".to_string(), "".to_string()).is_err());
+    }
+
+    #[test]
+    fn can_compile_full_match_regex() {
+        let regex = RegexLiteral::full_match("a|ab".to_string()).unwrap();
+        assert!(regex.is_match("ab"));
+        assert!(!regex.is_match("abc"));
+    }
+
     #[test]
     fn can_parse_single_array_index() {
         assert_eq!(
@@ -1,5 +1,9 @@
-use json_path::JsonPathQuery;
-use serde_json::json;
+use json_path::{
+    tokenizer::Tokenizer, FilterMode, FunctionRegistry, FunctionType, JsonPath, JsonPathError,
+    JsonPathQuery, NormalizedPath, ResultSink,
+};
+use serde_json::{json, Value};
+use std::ops::ControlFlow;
 
 #[test]
 fn json_path_query_api_works() {
@@ -7,3 +11,211 @@ fn json_path_query_api_works() {
     let result = json.query("$['greetings']");
     assert_eq!(Ok(json!("hello, json_path")), result);
 }
+
+#[test]
+fn compiled_json_path_can_be_shared_across_threads() {
+    let path = std::sync::Arc::new(JsonPath::parse("$.data[*].id").unwrap());
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || path.query(&json!({"data": [{"id": i}, {"id": i + 1}]})))
+        })
+        .collect();
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(Ok(json!([i, i + 1])), handle.join().unwrap());
+    }
+}
+
+#[test]
+fn json_path_supports_user_defined_functions() {
+    let mut functions = FunctionRegistry::new();
+    functions
+        .register(
+            "is_valid_sku",
+            vec![FunctionType::ValueType],
+            FunctionType::LogicalType,
+            |args| match &args[0] {
+                Value::String(sku) => Value::Bool(sku.starts_with("SKU-")),
+                _ => Value::Bool(false),
+            },
+        )
+        .unwrap()
+        .register(
+            "days_since",
+            vec![FunctionType::ValueType],
+            FunctionType::ValueType,
+            |args| match args[0].as_i64() {
+                Some(day) => Value::from(100 - day),
+                None => Value::Null,
+            },
+        )
+        .unwrap();
+    let tokenizer = Tokenizer::new().with_functions(functions);
+    let json = json!({"items": [
+        {"sku": "SKU-1", "created": 90},
+        {"sku": "sku-2", "created": 95},
+        {"sku": "SKU-3", "created": 10},
+    ]});
+
+    let path = JsonPath::parse_with(
+        "$.items[*][?(is_valid_sku(@.sku) && days_since(@.created) < 30)].sku",
+        &tokenizer,
+    )
+    .unwrap();
+    assert_eq!(Ok(json!(["SKU-1"])), path.query(&json));
+
+    let mut functions = FunctionRegistry::new();
+    functions
+        .register(
+            "holds",
+            vec![FunctionType::LogicalType],
+            FunctionType::LogicalType,
+            |args| args[0].clone(),
+        )
+        .unwrap();
+    let tokenizer = Tokenizer::new()
+        .with_functions(functions)
+        .with_filter_mode(FilterMode::Standard);
+    let json = json!([{"y": 1}, {"x": []}, {"x": false}, {"x": [1]}]);
+    let path = JsonPath::parse_with("$[?holds(@.x[*])]", &tokenizer).unwrap();
+    assert_eq!(Ok(json!([{"x": [1]}])), path.query(&json));
+    let path = JsonPath::parse_with("$[?holds(@.x)]", &tokenizer).unwrap();
+    assert_eq!(
+        Ok(json!([{"x": []}, {"x": false}, {"x": [1]}])),
+        path.query(&json)
+    );
+    let path = JsonPath::parse_with("$[?holds(!@.x)]", &tokenizer).unwrap();
+    assert_eq!(Ok(json!([{"y": 1}])), path.query(&json));
+
+    // unknown functions are rejected when parsing
+    let result = JsonPath::parse("$.items[*][?(is_valid_sku(@.sku))]");
+    assert_eq!(
+        Err(JsonPathError::InvalidJsonPath(
+            "Unknown function: is_valid_sku".to_string(),
+            13
+        )),
+        result
+    );
+}
+
+#[test]
+fn json_path_supports_mixed_union_selectors() {
+    let json = json!({"a": [{"x": 1}, {"name": "b"}, {"x": 3}, {"x": 4}]});
+    let tokenizer = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+    let path = JsonPath::parse_with("$.a[0, 'name', 2:4, *, ?@.x]", &tokenizer).unwrap();
+    assert_eq!(
+        Ok(json!([
+            {"x": 1},
+            {"x": 3},
+            {"x": 4},
+            {"x": 1},
+            {"name": "b"},
+            {"x": 3},
+            {"x": 4},
+            {"x": 1},
+            {"x": 3},
+            {"x": 4}
+        ])),
+        path.query(&json)
+    );
+    let located: Vec<String> = path
+        .query_located(&json)
+        .unwrap()
+        .into_iter()
+        .map(|(location, _)| location.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "$['a'][0]",
+            "$['a'][2]",
+            "$['a'][3]",
+            "$['a'][0]",
+            "$['a'][1]",
+            "$['a'][2]",
+            "$['a'][3]",
+            "$['a'][0]",
+            "$['a'][2]",
+            "$['a'][3]"
+        ],
+        located
+    );
+}
+
+#[test]
+fn json_path_evaluates_root_queries_in_filters_once() {
+    let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = calls.clone();
+    let mut functions = FunctionRegistry::new();
+    functions
+        .register(
+            "counted",
+            vec![FunctionType::ValueType],
+            FunctionType::LogicalType,
+            move |_| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Value::Bool(true)
+            },
+        )
+        .unwrap();
+    let tokenizer = Tokenizer::new()
+        .with_functions(functions)
+        .with_filter_mode(FilterMode::Standard);
+    let path = JsonPath::parse_with(
+        "$.items[?@.price < value($.limits[?counted(@)])].price",
+        &tokenizer,
+    )
+    .unwrap();
+    let json = json!({
+        "limits": {"max": 10},
+        "items": [{"price": 5}, {"price": 15}, {"price": 8}],
+    });
+    assert_eq!(Ok(json!([5, 8])), path.query(&json));
+    assert_eq!(1, calls.load(std::sync::atomic::Ordering::SeqCst));
+    assert_eq!(Ok(vec![&json!(5), &json!(8)]), path.query_ref(&json));
+    assert_eq!(2, calls.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[test]
+fn json_path_supports_filter_modes() {
+    let json = json!({"books": [{"isbn": "1", "title": "a"}, {"title": "b"}]});
+    // filters test the current node itself by default
+    let path = JsonPath::parse("$.books[*][?(@.isbn)].title").unwrap();
+    assert_eq!(Ok(json!(["a"])), path.query(&json));
+    let path = JsonPath::parse("$.books[0][?(@.isbn)].title").unwrap();
+    assert_eq!(Ok(json!("a")), path.query(&json));
+    assert_eq!(Ok(json!(["a"])), json.query("$.books[*][?(@.isbn)].title"));
+
+    let tokenizer = Tokenizer::new().with_filter_mode(FilterMode::Standard);
+    let path = JsonPath::parse_with("$.books[?(@.isbn)].title", &tokenizer).unwrap();
+    assert_eq!(Ok(json!(["a"])), path.query(&json));
+}
+
+#[test]
+fn json_path_can_reference_all_descendants() {
+    let json = json!({"store": {"book": [{"title": "a"}, {"title": "b"}]}});
+    let path = JsonPath::parse("$..*").unwrap();
+    let nodes: Vec<&Value> = path.query_ref(&json).unwrap();
+    assert_eq!(6, nodes.len());
+    assert_eq!(&json!("b"), nodes[5]);
+}
+
+#[test]
+fn json_path_can_stream_all_descendants_into_a_sink() {
+    let json = json!({"store": {"book": [{"title": "a"}, {"title": "b"}]}});
+
+    struct Count(usize);
+
+    impl<'a> ResultSink<'a> for Count {
+        fn accept(&mut self, _path: &NormalizedPath<'a>, _value: &'a Value) -> ControlFlow<()> {
+            self.0 += 1;
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut count = Count(0);
+    JsonPath::parse("$..*")
+        .unwrap()
+        .query_into(&json, &mut count)
+        .unwrap();
+    assert_eq!(6, count.0);
+}
@@ -1,7 +1,7 @@
-use json_path::JsonPathQuery;
+use json_path::{tokenizer::Tokenizer, FilterMode, JsonPath};
 use serde_json::Value;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::error::Error;
 use std::io;
 use std::str::FromStr;
@@ -12,6 +12,28 @@ struct Args {
     /// The jsonpath string.
     #[arg(short, long)]
     jsonpath: String,
+
+    /// How filters are applied.
+    #[arg(long, value_enum, default_value_t = FilterModeArg::Compat)]
+    filter_mode: FilterModeArg,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FilterModeArg {
+    /// a filter tests the current node itself, e.g. `$.books[*][?(@.isbn)]`
+    Compat,
+    /// a filter selects the members of arrays and objects as defined by RFC 9535, e.g.
+    /// `$.books[?@.isbn]`
+    Standard,
+}
+
+impl From<FilterModeArg> for FilterMode {
+    fn from(arg: FilterModeArg) -> Self {
+        match arg {
+            FilterModeArg::Compat => FilterMode::Compat,
+            FilterModeArg::Standard => FilterMode::Standard,
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -22,7 +44,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     stdin.read_line(&mut json)?;
 
     let value = Value::from_str(json.as_str())?;
-    let result = value.query(&args.jsonpath)?;
+    let tokenizer = Tokenizer::new().with_filter_mode(args.filter_mode.into());
+    let result = JsonPath::parse_with(&args.jsonpath, &tokenizer)?.query(&value)?;
     println!("{}", result);
     Ok(())
 }